@@ -0,0 +1,63 @@
+// kmeans.rs
+
+//! This module implements k-means clustering. Every restart seeds the centers with k-means++ and
+//! runs Lloyd's iterations, the restart with the lowest inertia is kept.
+
+use super::{num_clusters, Clusterer, Clustering};
+
+use crate::models::kmeans;
+use crate::types::Numeric;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct KMeans;
+
+impl Clusterer for KMeans {
+    fn cluster(
+        rows: &[Vec<Numeric>],
+        parameters: &Option<HashMap<String, String>>,
+    ) -> Result<Clustering, Box<dyn Error>> {
+        let num_clusters = num_clusters(rows, parameters)?;
+        let mut restarts = 10;
+        let mut max_iterations = 300;
+        let mut seed = 0;
+        if let Some(parameters) = parameters.as_ref() {
+            for (key, val) in parameters.iter() {
+                match key.as_str() {
+                    "restarts" => restarts = val.parse::<usize>()?.max(1),
+                    "max_iterations" => max_iterations = val.parse::<usize>()?,
+                    "seed" => seed = val.parse::<u64>()?,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut best: Option<(Clustering, f64)> = None;
+        for restart in 0..restarts {
+            let initial = kmeans::plus_plus(rows, num_clusters, &mut rng);
+            let (centers, assignments, inertia) = kmeans::lloyd(rows, initial, max_iterations);
+            println!("Restart {}: inertia: {}", restart, inertia);
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_inertia)| inertia < *best_inertia)
+            {
+                best = Some((
+                    Clustering {
+                        assignments,
+                        centers,
+                    },
+                    inertia,
+                ));
+            }
+        }
+
+        let (clustering, inertia) = best.unwrap();
+        println!("Kept the restart with inertia {}", inertia);
+        Ok(clustering)
+    }
+}
@@ -0,0 +1,133 @@
+// kmedoids.rs
+
+//! This module implements k-medoids clustering with Partitioning Around Medoids (PAM). The BUILD
+//! phase greedily picks the rows that lower the total distance of the rows to their closest
+//! medoid the most, the SWAP phase then exchanges a medoid with a non-medoid row as long as that
+//! lowers the total distance. Unlike k-means the centers are always rows of the table and the
+//! distances are not squared, which makes the clusters less sensitive to outliers.
+
+use super::{num_clusters, Clusterer, Clustering};
+
+use crate::models::kmeans::squared_distance;
+use crate::types::Numeric;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct KMedoids;
+
+impl Clusterer for KMedoids {
+    fn cluster(
+        rows: &[Vec<Numeric>],
+        parameters: &Option<HashMap<String, String>>,
+    ) -> Result<Clustering, Box<dyn Error>> {
+        let num_clusters = num_clusters(rows, parameters)?;
+        let mut max_iterations = 100;
+        if let Some(parameters) = parameters.as_ref() {
+            if let Some(val) = parameters.get("max_iterations") {
+                max_iterations = val.parse::<usize>()?;
+            }
+        }
+
+        let distances = rows
+            .iter()
+            .map(|x| rows.iter().map(|z| squared_distance(x, z).sqrt()).collect())
+            .collect::<Vec<Vec<f64>>>();
+
+        // BUILD: start with the most central row, then add the rows lowering the cost the most
+        let total = |row: &Vec<f64>| row.iter().sum::<f64>();
+        let first = (0..rows.len())
+            .min_by(|&x, &y| total(&distances[x]).total_cmp(&total(&distances[y])))
+            .unwrap();
+        let mut medoids = vec![first];
+        let mut nearest = distances[first].clone();
+        while medoids.len() < num_clusters {
+            let candidate = (0..rows.len())
+                .filter(|idx| !medoids.contains(idx))
+                .max_by(|&x, &y| {
+                    let gain = |candidate: usize| {
+                        nearest
+                            .iter()
+                            .zip(distances[candidate].iter())
+                            .map(|(current, distance)| (current - distance).max(0.0))
+                            .sum::<f64>()
+                    };
+                    gain(x).total_cmp(&gain(y))
+                })
+                .unwrap();
+            medoids.push(candidate);
+            for (current, distance) in nearest.iter_mut().zip(distances[candidate].iter()) {
+                *current = current.min(*distance);
+            }
+        }
+        println!("BUILD cost: {}", nearest.iter().sum::<f64>());
+
+        // SWAP: apply the best exchange of a medoid and a non-medoid until none lowers the cost
+        for iteration in 0..max_iterations {
+            let (closest, second) = closest_medoids(&distances, &medoids);
+            let mut best_change = 0.0;
+            let mut best_swap = None;
+            for position in 0..medoids.len() {
+                for candidate in (0..rows.len()).filter(|idx| !medoids.contains(idx)) {
+                    let change = (0..rows.len())
+                        .map(|idx| {
+                            let (medoid, distance) = closest[idx];
+                            let to_candidate = distances[idx][candidate];
+                            if medoid == position {
+                                // The row loses its medoid, it goes to the candidate or to its
+                                // second closest medoid
+                                to_candidate.min(second[idx]) - distance
+                            } else {
+                                (to_candidate - distance).min(0.0)
+                            }
+                        })
+                        .sum::<f64>();
+                    if change < best_change {
+                        best_change = change;
+                        best_swap = Some((position, candidate));
+                    }
+                }
+            }
+
+            match best_swap {
+                Some((position, candidate)) if best_change < -1e-12 => {
+                    medoids[position] = candidate;
+                    println!("Iteration {}: cost change: {}", iteration, best_change);
+                }
+                _ => break,
+            }
+        }
+
+        let (closest, _) = closest_medoids(&distances, &medoids);
+        println!(
+            "Final cost: {}",
+            closest.iter().map(|(_, distance)| distance).sum::<f64>()
+        );
+        Ok(Clustering {
+            assignments: closest.iter().map(|(medoid, _)| *medoid).collect(),
+            centers: medoids.iter().map(|&medoid| rows[medoid].clone()).collect(),
+        })
+    }
+}
+
+// Returns, for every row, the position of its closest medoid with the distance to it, and the
+// distance to its second closest medoid
+fn closest_medoids(distances: &[Vec<f64>], medoids: &[usize]) -> (Vec<(usize, f64)>, Vec<f64>) {
+    distances
+        .iter()
+        .map(|row| {
+            let mut closest = (0, f64::INFINITY);
+            let mut second = f64::INFINITY;
+            for (position, &medoid) in medoids.iter().enumerate() {
+                let distance = row[medoid];
+                if distance < closest.1 {
+                    second = closest.1;
+                    closest = (position, distance);
+                } else if distance < second {
+                    second = distance;
+                }
+            }
+            (closest, second)
+        })
+        .unzip()
+}
@@ -0,0 +1,159 @@
+// metrics.rs
+
+//! This module implements the scores of a clustering. Inertia, silhouette and Davies-Bouldin only
+//! look at the rows: inertia is the sum of squared distances of the rows to their center (lower is
+//! tighter), the silhouette compares the mean distance of a row to its own cluster with the mean
+//! distance to the closest other cluster (from -1 to 1, higher is better) and Davies-Bouldin
+//! averages, over the clusters, the worst ratio of within-cluster scatter to between-center
+//! distance (lower is better). Adjusted Rand index and purity compare the clusters with reference
+//! labels: the adjusted Rand index is the agreement on pairs of rows corrected for chance (1 for
+//! identical partitions, around 0 for random ones) and purity is the share of rows belonging to
+//! the most common label of their cluster.
+
+use super::Clustering;
+
+use crate::models::kmeans::squared_distance;
+use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+
+use std::collections::HashMap;
+
+pub fn inertia(rows: &[Vec<Numeric>], clustering: &Clustering) -> f64 {
+    rows.iter()
+        .zip(clustering.assignments.iter())
+        .map(|(row, &cluster)| squared_distance(row, &clustering.centers[cluster]))
+        .sum()
+}
+
+/// Mean silhouette over the rows, rows alone in their cluster count as zero
+pub fn silhouette(rows: &[Vec<Numeric>], clustering: &Clustering) -> f64 {
+    let num_clusters = clustering.centers.len();
+    if num_clusters < 2 {
+        return 0.0;
+    }
+    let sizes = cluster_sizes(clustering);
+
+    let total = rows
+        .iter()
+        .zip(clustering.assignments.iter())
+        .enumerate()
+        .map(|(idx, (row, &cluster))| {
+            if sizes[cluster] < 2 {
+                return 0.0;
+            }
+            let mut sums = vec![0.0; num_clusters];
+            for (other, (other_row, &other_cluster)) in
+                rows.iter().zip(clustering.assignments.iter()).enumerate()
+            {
+                if other != idx {
+                    sums[other_cluster] += squared_distance(row, other_row).sqrt();
+                }
+            }
+            let within = sums[cluster] / (sizes[cluster] - 1) as f64;
+            let between = (0..num_clusters)
+                .filter(|&other| other != cluster && sizes[other] > 0)
+                .map(|other| sums[other] / sizes[other] as f64)
+                .fold(f64::INFINITY, f64::min);
+            if !between.is_finite() {
+                return 0.0;
+            }
+            (between - within) / within.max(between)
+        })
+        .sum::<f64>();
+    total / rows.len() as f64
+}
+
+pub fn davies_bouldin(rows: &[Vec<Numeric>], clustering: &Clustering) -> f64 {
+    let sizes = cluster_sizes(clustering);
+    let mut scatter = vec![0.0; clustering.centers.len()];
+    for (row, &cluster) in rows.iter().zip(clustering.assignments.iter()) {
+        scatter[cluster] += squared_distance(row, &clustering.centers[cluster]).sqrt();
+    }
+    for (scatter, &size) in scatter.iter_mut().zip(sizes.iter()) {
+        if size > 0 {
+            *scatter /= size as f64;
+        }
+    }
+
+    // Empty clusters take no part in the index
+    let clusters = (0..clustering.centers.len())
+        .filter(|&cluster| sizes[cluster] > 0)
+        .collect::<Vec<usize>>();
+    if clusters.len() < 2 {
+        return 0.0;
+    }
+    clusters
+        .iter()
+        .map(|&i| {
+            clusters
+                .iter()
+                .filter(|&&j| j != i)
+                .map(|&j| {
+                    let separation =
+                        squared_distance(&clustering.centers[i], &clustering.centers[j]).sqrt();
+                    (scatter[i] + scatter[j]) / separation
+                })
+                .fold(0.0, f64::max)
+        })
+        .sum::<f64>()
+        / clusters.len() as f64
+}
+
+pub fn adjusted_rand_index(assignments: &[usize], labels: &[Numeric]) -> f64 {
+    let pairs = |count: usize| (count * count.saturating_sub(1)) as f64 / 2.0;
+    if assignments.len() < 2 {
+        // There are no pairs of rows to agree or disagree on
+        return 1.0;
+    }
+
+    let contingency = contingency(assignments, labels);
+    let mut cluster_totals: HashMap<usize, usize> = HashMap::new();
+    let mut label_totals: HashMap<i64, usize> = HashMap::new();
+    for (&(cluster, label), &count) in contingency.iter() {
+        *cluster_totals.entry(cluster).or_insert(0) += count;
+        *label_totals.entry(label).or_insert(0) += count;
+    }
+
+    let index = contingency.values().map(|&count| pairs(count)).sum::<f64>();
+    let cluster_pairs = cluster_totals
+        .values()
+        .map(|&count| pairs(count))
+        .sum::<f64>();
+    let label_pairs = label_totals
+        .values()
+        .map(|&count| pairs(count))
+        .sum::<f64>();
+    let expected = cluster_pairs * label_pairs / pairs(assignments.len());
+    let maximum = (cluster_pairs + label_pairs) / 2.0;
+    if maximum == expected {
+        // Both partitions are trivial (a single group, or every row on its own)
+        return 1.0;
+    }
+    (index - expected) / (maximum - expected)
+}
+
+pub fn purity(assignments: &[usize], labels: &[Numeric]) -> f64 {
+    let mut majority: HashMap<usize, usize> = HashMap::new();
+    for (&(cluster, _), &count) in contingency(assignments, labels).iter() {
+        let best = majority.entry(cluster).or_insert(0);
+        *best = (*best).max(count);
+    }
+    majority.values().sum::<usize>() as f64 / assignments.len() as f64
+}
+
+// Returns the number of rows of every cluster and label pair
+fn contingency(assignments: &[usize], labels: &[Numeric]) -> HashMap<(usize, i64), usize> {
+    let mut counts = HashMap::new();
+    for (&cluster, label) in assignments.iter().zip(labels.iter()) {
+        let key = (label / NUMERIC_DIGIT_PRECISION) as i64;
+        *counts.entry((cluster, key)).or_insert(0) += 1;
+    }
+    counts
+}
+
+fn cluster_sizes(clustering: &Clustering) -> Vec<usize> {
+    let mut sizes = vec![0; clustering.centers.len()];
+    for &cluster in clustering.assignments.iter() {
+        sizes[cluster] += 1;
+    }
+    sizes
+}
@@ -0,0 +1,120 @@
+//! This module contains the unsupervised learning logic: the rows of the table are grouped into
+//! clusters over every column but the label, and the clusters are scored on their own
+//! (inertia, silhouette, Davies-Bouldin) and, when a label column is given, against it (adjusted
+//! Rand index, purity).
+
+mod kmeans;
+mod kmedoids;
+pub mod metrics;
+
+use crate::config::ClusteringConfigs;
+
+use crate::data::data_frame::DataFrame;
+use crate::types::Numeric;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Result of a clustering: the cluster of every row and the center of every cluster
+#[derive(Debug)]
+pub struct Clustering {
+    pub assignments: Vec<usize>,
+    pub centers: Vec<Vec<Numeric>>,
+}
+
+pub trait Clusterer {
+    fn cluster(
+        rows: &[Vec<Numeric>],
+        parameters: &Option<HashMap<String, String>>,
+    ) -> Result<Clustering, Box<dyn Error>>;
+}
+
+type ClusterFnPtr = fn(&[Vec<Numeric>], &Option<HashMap<String, String>>) -> Result<Clustering, Box<dyn Error>>;
+
+pub fn get_clusterer(name: &str) -> Result<ClusterFnPtr, Box<dyn Error>> {
+    match name {
+        "kmeans" => Ok(kmeans::KMeans::cluster),
+        "kmedoids" => Ok(kmedoids::KMedoids::cluster),
+        _ => Err(format!("Invalid clustering name given: {}", name).into()),
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusteringScores {
+    pub inertia: f64,
+    pub silhouette: f64,
+    pub davies_bouldin: f64,
+    pub adjusted_rand_index: Option<f64>,
+    pub purity: Option<f64>,
+}
+
+/// Clusters the rows of the table and scores the clusters
+pub fn cluster_and_evaluate(
+    df: &DataFrame<Numeric>,
+    configs: &ClusteringConfigs,
+) -> Result<ClusteringScores, Box<dyn Error>> {
+    let label_index = configs.label_index;
+    if let Some(label_index) = label_index {
+        if df.get_column_idx(label_index).is_none() {
+            return Err("Couldn't find index of column of reference labels!".into());
+        }
+    }
+
+    let num_rows = df.get_column_idx(0).map_or(0, |column| column.values().count());
+    if num_rows == 0 {
+        return Err("No rows to cluster!".into());
+    }
+    let mut rows = Vec::with_capacity(num_rows);
+    let mut labels = Vec::with_capacity(num_rows);
+    for idx in 0..num_rows {
+        let row = df.get_row(idx)?;
+        if let Some(label_index) = label_index {
+            labels.push(row[label_index]);
+        }
+        rows.push(
+            row.into_iter()
+                .enumerate()
+                .filter(|&(column, _)| Some(column) != label_index)
+                .map(|(_, value)| value)
+                .collect::<Vec<Numeric>>(),
+        );
+    }
+
+    let cluster = get_clusterer(&configs.name)?;
+    let clustering = cluster(&rows, &configs.parameters)?;
+
+    for (position, _) in clustering.centers.iter().enumerate() {
+        let size = clustering
+            .assignments
+            .iter()
+            .filter(|&&assignment| assignment == position)
+            .count();
+        println!("Cluster {}: {} rows", position, size);
+    }
+
+    let reference = label_index.map(|_| labels.as_slice());
+    Ok(ClusteringScores {
+        inertia: metrics::inertia(&rows, &clustering),
+        silhouette: metrics::silhouette(&rows, &clustering),
+        davies_bouldin: metrics::davies_bouldin(&rows, &clustering),
+        adjusted_rand_index: reference
+            .map(|labels| metrics::adjusted_rand_index(&clustering.assignments, labels)),
+        purity: reference.map(|labels| metrics::purity(&clustering.assignments, labels)),
+    })
+}
+
+/// Returns the number of clusters asked for in the parameters
+pub fn num_clusters(
+    rows: &[Vec<Numeric>],
+    parameters: &Option<HashMap<String, String>>,
+) -> Result<usize, Box<dyn Error>> {
+    let num_clusters = parameters
+        .as_ref()
+        .and_then(|parameters| parameters.get("num_clusters"))
+        .ok_or("The clustering stage needs the num_clusters parameter!")?
+        .parse::<usize>()?;
+    if num_clusters == 0 || num_clusters > rows.len() {
+        return Err("The number of clusters must be between one and the number of rows!".into());
+    }
+    Ok(num_clusters)
+}
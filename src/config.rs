@@ -7,7 +7,7 @@ use std::collections::HashMap;
 use serde::Deserialize;
 
 // Structure defining fields in the validation sub-field of model stage
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ValidationConfigs {
     pub strategy: String,
     pub parameters: HashMap<String, f64>,
@@ -17,11 +17,11 @@ pub struct ValidationConfigs {
 pub struct ModelConfigs {
     pub name: String,
     pub task: String,
-    pub parameters: Option<HashMap<String, f64>>,
-    pub tuning: HashMap<String, Vec<f64>>,
+    pub parameters: Option<HashMap<String, String>>,
+    pub tuning: HashMap<String, Vec<String>>,
 }
 
-// Structure defining fields in the mandatory model stage
+// Structure defining fields in the supervised model stage
 #[derive(Debug, Deserialize)]
 pub struct TrainingConfigs {
     pub model: ModelConfigs,
@@ -29,6 +29,8 @@ pub struct TrainingConfigs {
     pub partitioning: ValidationConfigs,
     pub strategy: String,
     pub label_index: usize,
+    // File the best trained model is saved to, with the state of the preprocessing stages
+    pub save: Option<String>,
 }
 
 // Structure defining fields in the mandatory input stage
@@ -51,8 +53,27 @@ pub struct ScrubbingStageConfigs {
 #[derive(Debug, Deserialize)]
 pub struct TransformStageConfigs {
     pub name: String,
-    pub index: usize,
-    pub parameters: Option<HashMap<String, f64>>
+    // Column to transform, not used by transforms working on the whole table
+    pub index: Option<usize>,
+    pub parameters: Option<HashMap<String, f64>>,
+    // Settings that aren't numbers, such as the activation and hidden layers of the autoencoder
+    pub hyperparameters: Option<HashMap<String, String>>,
+}
+
+// Structure defining fields in the feature selection stage
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectionStageConfigs {
+    pub name: String,
+    pub parameters: Option<HashMap<String, f64>>,
+}
+
+// Structure defining fields in the unsupervised clustering stage
+#[derive(Debug, Deserialize)]
+pub struct ClusteringConfigs {
+    pub name: String,
+    pub parameters: Option<HashMap<String, String>>,
+    // Column left out of the clustering and used as reference labels, if any
+    pub label_index: Option<usize>,
 }
 
 // Overall structure defining the configuration stages
@@ -62,5 +83,31 @@ pub struct ConfigStruct {
     pub parsing: Vec<String>,
     pub scrub: Option<Vec<ScrubbingStageConfigs>>,
     pub transform: Option<Vec<TransformStageConfigs>>,
-    pub training: TrainingConfigs,
+    pub selection: Option<Vec<SelectionStageConfigs>>,
+    pub training: Option<TrainingConfigs>,
+    pub clustering: Option<ClusteringConfigs>,
+}
+
+impl ConfigStruct {
+    /// Returns the index of the label column: the target of the training stage, or the optional
+    /// reference labels of the clustering stage
+    pub fn label_index(&self) -> Option<usize> {
+        match (self.training.as_ref(), self.clustering.as_ref()) {
+            (Some(training), _) => Some(training.label_index),
+            (None, Some(clustering)) => clustering.label_index,
+            (None, None) => None,
+        }
+    }
+
+    /// Updates the index of the label column after a stage moved it
+    pub fn set_label_index(&mut self, label_index: usize) {
+        if let Some(training) = self.training.as_mut() {
+            training.label_index = label_index;
+        }
+        if let Some(clustering) = self.clustering.as_mut() {
+            if clustering.label_index.is_some() {
+                clustering.label_index = Some(label_index);
+            }
+        }
+    }
 }
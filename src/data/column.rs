@@ -7,6 +7,8 @@
 /// column. It also has abstractions to manipulate and retrieve individual elements in a
 /// cell.
 
+use serde::{Deserialize, Serialize};
+
 use std::collections::HashMap;
 use std::fmt;
 
@@ -19,6 +21,41 @@ pub struct Column<T: Sized> {
     values: Vec<T>,
 }
 
+/// Describes a column without its values. Used by stages that need to know the name of a column
+/// and how it was encoded, for example to treat ordinal/nominal columns as categorical.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColumnInfo {
+    pub name: Option<String>,
+    pub metadata: Option<HashMap<u32, String>>,
+}
+
+impl ColumnInfo {
+    // Only the ordinal and nominal parsers attach a value map to the column
+    pub fn is_categorical(&self) -> bool {
+        self.metadata.is_some()
+    }
+
+    /// Returns the original string of a coded value, if the column has a value map
+    pub fn value_name(&self, value: f64) -> Option<&str> {
+        if value < 0.0 || value.fract() != 0.0 {
+            return None;
+        }
+        self.metadata
+            .as_ref()?
+            .get(&(value as u32))
+            .map(|name| name.as_str())
+    }
+
+    /// Returns the coded value of an original string, if the column has a value map with it
+    pub fn value_code(&self, name: &str) -> Option<f64> {
+        self.metadata
+            .as_ref()?
+            .iter()
+            .find(|(_, value)| value.as_str() == name)
+            .map(|(&code, _)| f64::from(code))
+    }
+}
+
 impl<T> Default for Column<T> {
     fn default() -> Self {
         Self::new()
@@ -71,6 +108,13 @@ impl<T> Column<T> {
         }
     }
 
+    pub fn info(&self) -> ColumnInfo {
+        ColumnInfo {
+            name: self.name.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
     pub fn get(&self, index: usize) -> Option<&T> {
         self.values.get(index)
     }
@@ -5,7 +5,7 @@
 /// an index into the array of columns. It provides iterators so that algorithms can iterate
 /// over the columns and itself provides the abstraction to get a row from the table.
 
-use crate::data::column::Column;
+use crate::data::column::{Column, ColumnInfo};
 
 use std::collections::HashMap;
 use std::error::Error;
@@ -53,6 +53,19 @@ where T: Clone {
         Ok(df)
     }
 
+    /// Sets the names and value maps of the columns, for tables built from rows
+    pub fn set_columns_info(&mut self, columns: &[ColumnInfo]) {
+        for (idx, (column, info)) in self.columns.iter_mut().zip(columns.iter()).enumerate() {
+            if let Some(name) = info.name.as_ref() {
+                column.set_name(name.clone());
+                self.column_idx_map.insert(name.clone(), idx);
+            }
+            if let Some(metadata) = info.metadata.as_ref() {
+                column.set_metadata(metadata.clone());
+            }
+        }
+    }
+
     pub fn columns(&self) -> std::slice::Iter<'_, Column<T>> {
         self.columns.iter()
     }
@@ -61,6 +74,10 @@ where T: Clone {
         self.columns.iter_mut()
     }
 
+    pub fn columns_info(&self) -> Vec<ColumnInfo> {
+        self.columns.iter().map(|column| column.info()).collect()
+    }
+
     pub fn add_column(&mut self, column: Column<T>) {
         if let Some(name) = column.get_name() {
             self.column_idx_map
@@ -69,6 +86,22 @@ where T: Clone {
         self.columns.push(column);
     }
 
+    pub fn remove_column(&mut self, idx: usize) -> Option<Column<T>> {
+        if idx >= self.columns.len() {
+            return None;
+        }
+        let column = self.columns.remove(idx);
+
+        // Indexes after the removed column shift down, so the name map has to be rebuilt
+        self.column_idx_map.clear();
+        for (idx, column) in self.columns.iter().enumerate() {
+            if let Some(name) = column.get_name() {
+                self.column_idx_map.insert(name.to_owned(), idx);
+            }
+        }
+        Some(column)
+    }
+
     pub fn get_column_idx(&self, idx: usize) -> Option<&Column<T>> {
         self.columns.get(idx)
     }
@@ -0,0 +1,47 @@
+// brier_score.rs
+
+//! This evaluator implements the multiclass Brier score, the squared difference between the
+//! predicted class probabilities and the one-hot encoding of the true class, summed over the
+//! classes and averaged over the samples.
+
+use super::ProbabilityEvaluator;
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use std::error::Error;
+
+pub struct BrierScoreEvaluator;
+
+impl ProbabilityEvaluator for BrierScoreEvaluator {
+    fn evaluate(
+        probabilities: &[ClassProbabilities],
+        training_samples: &[Box<[Numeric]>],
+        training_label_idx: usize,
+    ) -> Result<f64, Box<dyn Error>> {
+        if probabilities.len() != training_samples.len() {
+            return Err("Predictions and targets are not of the same size!".into());
+        }
+
+        let score = probabilities
+            .iter()
+            .zip(training_samples.iter())
+            .fold(0.0, |acc, (distribution, target)| {
+                let mut true_class_found = false;
+                let mut error = distribution.iter().fold(0.0, |acc, (class, probability)| {
+                    if (class - target[training_label_idx]).abs() < NUMERIC_DIGIT_PRECISION {
+                        true_class_found = true;
+                        acc + (1.0 - probability) * (1.0 - probability)
+                    } else {
+                        acc + probability * probability
+                    }
+                });
+                // A true class missing from the distribution was given zero probability
+                if !true_class_found {
+                    error += 1.0;
+                }
+                acc + error
+            })
+            / training_samples.len() as f64;
+
+        Ok(score)
+    }
+}
@@ -0,0 +1,43 @@
+// log_loss.rs
+
+//! This evaluator implements the logarithmic loss (cross-entropy) of the predicted class
+//! probabilities. The probability given to the true class of each sample is clipped so a confident
+//! wrong prediction gives a large but finite loss.
+
+use super::ProbabilityEvaluator;
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use std::error::Error;
+
+const MIN_PROBABILITY: f64 = 1e-15;
+
+pub struct LogLossEvaluator;
+
+impl ProbabilityEvaluator for LogLossEvaluator {
+    fn evaluate(
+        probabilities: &[ClassProbabilities],
+        training_samples: &[Box<[Numeric]>],
+        training_label_idx: usize,
+    ) -> Result<f64, Box<dyn Error>> {
+        if probabilities.len() != training_samples.len() {
+            return Err("Predictions and targets are not of the same size!".into());
+        }
+
+        // Accumulate the negative log of the probability given to the true class
+        let loss = probabilities
+            .iter()
+            .zip(training_samples.iter())
+            .fold(0.0, |acc, (distribution, target)| {
+                let probability = distribution
+                    .iter()
+                    .find(|(class, _)| {
+                        (class - target[training_label_idx]).abs() < NUMERIC_DIGIT_PRECISION
+                    })
+                    .map_or(0.0, |(_, probability)| *probability);
+                acc - probability.clamp(MIN_PROBABILITY, 1.0).ln()
+            })
+            / training_samples.len() as f64;
+
+        Ok(loss)
+    }
+}
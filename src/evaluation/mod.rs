@@ -1,9 +1,11 @@
 //! This module containes the evaluation strategies for a machine learning model
 
+mod brier_score;
 mod classification_score;
+mod log_loss;
 mod mse;
 
-use crate::types::Numeric;
+use crate::types::{ClassProbabilities, Numeric};
 
 use std::error::Error;
 
@@ -15,12 +17,30 @@ pub trait Evaluator {
     ) -> Result<f64, Box<dyn Error>>;
 }
 
+pub trait ProbabilityEvaluator {
+    fn evaluate(
+        probabilities: &[ClassProbabilities],
+        training_samples: &[Box<[Numeric]>],
+        training_label_idx: usize,
+    ) -> Result<f64, Box<dyn Error>>;
+}
+
 pub type EvaluationFnPtr = fn(&[Numeric], &[Box<[Numeric]>], usize) -> Result<f64, Box<dyn Error>>;
 
-pub fn get_evaluator(name: &str) -> Result<EvaluationFnPtr, Box<dyn Error>> {
+pub type ProbabilityEvaluationFnPtr = fn(&[ClassProbabilities], &[Box<[Numeric]>], usize) -> Result<f64, Box<dyn Error>>;
+
+/// Evaluation strategies either score the predicted values or the predicted class probabilities
+pub enum EvaluationStrategy {
+    Predictions(EvaluationFnPtr),
+    Probabilities(ProbabilityEvaluationFnPtr),
+}
+
+pub fn get_evaluator(name: &str) -> Result<EvaluationStrategy, Box<dyn Error>> {
     match name {
-        "classification-score" => Ok(classification_score::ClassificationScoreEvaluator::evaluate),
-        "mse" => Ok(mse::MeanSquaredErrorEvaluator::evaluate),
+        "classification-score" => Ok(EvaluationStrategy::Predictions(classification_score::ClassificationScoreEvaluator::evaluate)),
+        "mse" => Ok(EvaluationStrategy::Predictions(mse::MeanSquaredErrorEvaluator::evaluate)),
+        "log-loss" => Ok(EvaluationStrategy::Probabilities(log_loss::LogLossEvaluator::evaluate)),
+        "brier-score" => Ok(EvaluationStrategy::Probabilities(brier_score::BrierScoreEvaluator::evaluate)),
         _ => Err("Evaluation strategy not supported: {name}".into())
     }
 
@@ -2,12 +2,17 @@
 
 /// This file specifies the different modules in the project
 
+pub mod clustering;
 pub mod data;
 pub mod evaluation;
 pub mod input;
 pub mod models;
 pub mod parsers;
+pub mod persistence;
+pub mod predict;
 pub mod scrubbers;
+pub mod selection;
+pub mod serve;
 pub mod transform;
 pub mod types;
 pub mod validation;
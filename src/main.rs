@@ -1,13 +1,20 @@
 // main.rs
 
+use std::collections::HashMap;
 use std::env;
 use std::error::Error;
 use std::fs::File;
 
+use pipeline::clustering;
 use pipeline::config::ConfigStruct;
 use pipeline::input;
+use pipeline::models::SavedModel;
 use pipeline::parsers;
+use pipeline::persistence::{self, SavedPipeline, ScrubbedColumn};
+use pipeline::predict;
 use pipeline::scrubbers;
+use pipeline::selection;
+use pipeline::serve;
 use pipeline::trainers;
 use pipeline::transform;
 
@@ -19,9 +26,37 @@ fn main() -> Result<(), Box<dyn Error>> {
         return Err("No command line arguments given!".into());
     }
 
+    // Score new data with a saved model instead of running an experiment
+    if args[1] == "predict" {
+        let options = parse_options(&args[2..])?;
+        let model = options
+            .get("model")
+            .ok_or("predict needs the saved model file: --model <file>")?;
+        let input = options
+            .get("input")
+            .ok_or("predict needs the file to score: --input <csv>")?;
+        let output = options
+            .get("output")
+            .map_or("predictions.csv", |output| output.as_str());
+        return predict::predict(model, input, output);
+    }
+
+    // Serve a saved model over HTTP on localhost
+    if args[1] == "serve" {
+        let options = parse_options(&args[2..])?;
+        let model = options
+            .get("model")
+            .ok_or("serve needs the saved model file: --model <file>")?;
+        let port = match options.get("port") {
+            Some(port) => port.parse::<u16>()?,
+            None => 8080,
+        };
+        return serve::serve(model, port);
+    }
+
     // Open file specified on the path given as argument in the command line
     let file = File::open(&args[1])?;
-    let configs: ConfigStruct = serde_yaml::from_reader(file)?;
+    let mut configs: ConfigStruct = serde_yaml::from_reader(file)?;
 
     println!("######################################");
     println!("#############   INPUT   ##############");
@@ -49,19 +84,29 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("{}", col);
     }
 
+    // Keep the parsed column names and value maps, and the label index before any stage moves it,
+    // so new data can be encoded the same way
+    let parsed_columns = parsed.columns_info();
+    let input_label_index = configs.label_index();
+
     println!("######################################");
     println!("############# SCRUBBING ##############");
     println!("######################################");
 
     // Scrubbing stage, this stage replaces missing values and all missing
     // values are dealt with
+    let mut scrubbed = Vec::new();
     if let Some(configs) = configs.scrub.as_ref() {
         // There was a scrub stage specified in the configuration file, iterate through each scrubber
         // and clean features accordingly
         for config in configs {
             let scrubber = scrubbers::get_scrubber(&config.name)?;
             if let Some(column) = parsed.get_column_idx_mut(config.index) {
-                scrubber(column)?;
+                let value = scrubber(column)?;
+                scrubbed.push(ScrubbedColumn {
+                    index: config.index,
+                    value,
+                });
             }
         }
     }
@@ -73,23 +118,126 @@ fn main() -> Result<(), Box<dyn Error>> {
     }
 
     // Transform stage, this stage performs operations to the numbers
-    if let Some(configs) = configs.transform.as_ref() {
+    let mut fitted_stages = Vec::new();
+    if let Some(transform_configs) = configs.transform.as_ref() {
         println!("######################################");
         println!("############# TRANSFORM ##############");
         println!("######################################");
-        transform::apply(&mut cleaned, configs)?;
+        let (label_index, stages) =
+            transform::apply(&mut cleaned, configs.label_index(), transform_configs)?;
+        if let Some(label_index) = label_index {
+            configs.set_label_index(label_index);
+        }
+        fitted_stages = stages;
 
         for col in cleaned.columns() {
             println!("{}", col);
         }
     }
 
-    println!("######################################");
-    println!("############# TRAINING  ##############");
-    println!("######################################");
+    // Selection stage, this stage removes feature columns that shouldn't be used for training.
+    // Supervised selectors use the label to score features, so they are left to the training stage
+    // which runs them on the training rows of every fold.
+    let mut selected = None;
+    let mut supervised = Vec::new();
+    if let Some(selection_configs) = configs.selection.as_ref() {
+        println!("######################################");
+        println!("############# SELECTION ##############");
+        println!("######################################");
+        let training = configs
+            .training
+            .as_ref()
+            .ok_or("Feature selection needs a training stage with a label column!")?;
+        let (supervised_configs, filter_configs): (Vec<_>, Vec<_>) = selection_configs
+            .iter()
+            .cloned()
+            .partition(|config| selection::is_supervised(&config.name));
+        supervised = supervised_configs;
+
+        if !filter_configs.is_empty() {
+            let (label_index, kept) = selection::apply(&mut cleaned, training, &filter_configs)?;
+            configs.set_label_index(label_index);
+            selected = Some(kept);
+
+            for col in cleaned.columns() {
+                println!("{}", col);
+            }
+        }
+    }
+
+    if configs.training.is_none() && configs.clustering.is_none() {
+        return Err("No training or clustering stage given!".into());
+    }
+
+    // Clustering stage, this stage groups the rows without using the label column
+    if let Some(clustering_configs) = configs.clustering.as_ref() {
+        println!("######################################");
+        println!("############# CLUSTERING #############");
+        println!("######################################");
+
+        let scores = clustering::cluster_and_evaluate(&cleaned, clustering_configs)?;
+        println!("Clustering scores:\n{:#?}", scores);
+    }
+
+    if let Some(training_configs) = configs.training.as_ref() {
+        println!("######################################");
+        println!("############# TRAINING  ##############");
+        println!("######################################");
 
-    let validation_performance = trainers::train_and_evaluate(&cleaned, &configs)?;
-    println!("Validation performance: {}", validation_performance);
+        let (validation_performance, best) =
+            trainers::train_and_evaluate(&cleaned, training_configs, &supervised)?;
+        println!("Validation performance: {}", validation_performance);
+
+        if let Some(path) = training_configs.save.as_ref() {
+            // Columns chosen by the supervised selectors index the table left by the other selectors
+            let (selection, label_index) = match best.features {
+                Some(features) => {
+                    let columns = match selected.as_ref() {
+                        Some(kept) => features.columns.iter().map(|&column| kept[column]).collect(),
+                        None => features.columns,
+                    };
+                    (Some(columns), features.label_index)
+                }
+                None => (selected, training_configs.label_index),
+            };
+            let saved = SavedPipeline {
+                version: persistence::FORMAT_VERSION,
+                format: configs.input.format.clone(),
+                missing_values: configs.input.missing_values.clone(),
+                headers: configs.input.headers,
+                parsing: configs.parsing.clone(),
+                columns: parsed_columns,
+                scrub: scrubbed,
+                transform: fitted_stages,
+                selection,
+                input_label_index: input_label_index
+                    .ok_or("A trained model needs a label column!")?,
+                label_index,
+                task: training_configs.model.task.clone(),
+                model_name: training_configs.model.name.clone(),
+                hyperparameters: best.model.get_hyperparameters(),
+                model: SavedModel::new(best.model.as_ref())?,
+            };
+            persistence::save(path, &saved)?;
+            println!("Saved the best model to {}", path);
+        }
+    }
 
     Ok(())
 }
+
+// Reads `--name value` pairs of command line options
+fn parse_options(args: &[String]) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut options = HashMap::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let name = arg
+            .strip_prefix("--")
+            .ok_or(format!("Unexpected command line argument: {}", arg))?;
+        let value = args
+            .next()
+            .ok_or(format!("Missing value for the --{} option!", name))?;
+        options.insert(name.to_string(), value.clone());
+    }
+    Ok(options)
+}
@@ -0,0 +1,321 @@
+// adaboost.rs
+
+//! This file implements AdaBoost with the SAMME rule, which handles any number of classes. Every
+//! round fits the base learner on weighted training values, then gives the learner a say that grows
+//! with its weighted accuracy and raises the weights of the training values it misclassified, so
+//! the next learner focuses on them. A learner only needs to beat random guessing (a weighted error
+//! below (K - 1)/K for K classes), otherwise boosting stops. The base learner is any model builder;
+//! builders that can't learn from sample weights are given a resample of the training values drawn
+//! with probabilities proportional to the weights. The base learner hyperparameters use the `base`
+//! prefix (e.g. `base.max_depth`), and the default decision tree base learner is a stump.
+
+use super::{get_model_builder, training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Serialize, Deserialize)]
+pub struct AdaBoost {
+    pub label_index: usize,
+    // Model name of the base learner
+    pub base: String,
+    pub num_rounds: usize,
+    // Shrinks the say of every learner, smaller values need more rounds
+    pub learning_rate: f64,
+    pub seed: u64,
+    // Hyperparameters of the base learner, keyed without the prefix
+    base_hyperparameters: HashMap<String, String>,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    // Every learner with its say in the vote
+    learners: Vec<(Box<dyn Model>, f64)>,
+}
+
+impl Model for AdaBoost {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        // Return the class with the largest weighted vote, ties go to the smallest class
+        let scores = self.scores(sample);
+        let best = scores
+            .iter()
+            .enumerate()
+            .fold(0, |best, (position, &score)| {
+                if score > scores[best] {
+                    position
+                } else {
+                    best
+                }
+            });
+        self.classes[best]
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        self.label(sample)
+    }
+
+    // Returns the share of the weighted vote each class gets
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        let scores = self.scores(sample);
+        let total = scores.iter().sum::<f64>();
+        self.classes
+            .iter()
+            .zip(scores.iter())
+            .map(|(&class, score)| {
+                let share = if total > 0.0 {
+                    score / total
+                } else {
+                    1.0 / self.classes.len() as f64
+                };
+                (class, share)
+            })
+            .collect()
+    }
+
+    fn type_id(&self) -> &'static str {
+        "AdaBoost"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        let mut hyperparameters = self
+            .base_hyperparameters
+            .iter()
+            .map(|(key, val)| (format!("base.{}", key), val.clone()))
+            .collect::<HashMap<String, String>>();
+        hyperparameters.insert("base".into(), self.base.clone());
+        hyperparameters.insert("num_rounds".into(), self.num_rounds.to_string());
+        hyperparameters.insert("learning_rate".into(), self.learning_rate.to_string());
+        hyperparameters.insert("seed".into(), self.seed.to_string());
+        hyperparameters
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "base" => {
+                    self.base = val.clone();
+                }
+                "num_rounds" => {
+                    self.num_rounds = val.parse::<usize>()?;
+                    if self.num_rounds == 0 {
+                        return Err("AdaBoost needs at least one round!".into());
+                    }
+                }
+                "learning_rate" => {
+                    self.learning_rate = val.parse::<f64>()?;
+                    if self.learning_rate <= 0.0 {
+                        return Err("The learning rate must be positive!".into());
+                    }
+                }
+                "seed" => {
+                    self.seed = val.parse::<u64>()?;
+                }
+                _ => {
+                    if let Some(name) = key.strip_prefix("base.") {
+                        self.base_hyperparameters.insert(name.to_string(), val.clone());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AdaBoost {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            base: "decision-tree".to_string(),
+            num_rounds: 50,
+            learning_rate: 1.0,
+            seed: 0,
+            base_hyperparameters: HashMap::new(),
+            classes: Vec::new(),
+            learners: Vec::new(),
+        }
+    }
+
+    /// Boosts the base learner over the training values. Must be called after the
+    /// hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        if context.task.as_deref().is_some_and(|task| task != "classification") {
+            return Err("AdaBoost only supports classification".into());
+        }
+        self.classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+        if self.classes.len() < 2 {
+            return Err("AdaBoost needs at least two classes!".into());
+        }
+
+        let mut base_hyperparameters = self.base_hyperparameters.clone();
+        if self.base == "decision-tree" {
+            base_hyperparameters
+                .entry("max_depth".to_string())
+                .or_insert_with(|| "1".to_string());
+        }
+
+        let num_classes = self.classes.len() as f64;
+        let mut weights = vec![1.0 / training_values.len() as f64; training_values.len()];
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.learners.clear();
+        for round in 0..self.num_rounds {
+            let mut builder = get_model_builder(&self.base)?;
+            builder.with_context(context)?;
+            builder.with_hyperparameters(&base_hyperparameters)?;
+            let learner = if builder.supports_sample_weights() {
+                builder.build(training_values, self.label_index, Some(&weights))?
+            } else {
+                let distribution = WeightedIndex::new(&weights)?;
+                let resample = (0..training_values.len())
+                    .map(|_| training_values[distribution.sample(&mut rng)].clone())
+                    .collect::<Vec<Box<[Numeric]>>>();
+                builder.build(&resample, self.label_index, None)?
+            };
+
+            let misses = training_values
+                .iter()
+                .map(|sample| {
+                    (learner.label(sample) - sample[self.label_index]).abs()
+                        >= NUMERIC_DIGIT_PRECISION
+                })
+                .collect::<Vec<bool>>();
+            let error = weights
+                .iter()
+                .zip(misses.iter())
+                .filter(|(_, &miss)| miss)
+                .map(|(weight, _)| weight)
+                .sum::<f64>()
+                / weights.iter().sum::<f64>();
+
+            // A perfect learner settles the vote on its own, the earlier learners are dropped so
+            // they can't outvote it
+            if error <= NUMERIC_DIGIT_PRECISION {
+                println!("Round {}: weighted error: {}, stopping early", round, error);
+                self.learners.clear();
+                self.learners.push((learner, 1.0));
+                break;
+            }
+            // A learner no better than chance can't be boosted
+            if error >= 1.0 - 1.0 / num_classes {
+                println!(
+                    "Round {}: weighted error {} is no better than chance, stopping",
+                    round, error
+                );
+                if self.learners.is_empty() {
+                    return Err(
+                        "The base learner is no better than chance on the training values!".into(),
+                    );
+                }
+                break;
+            }
+
+            let say =
+                self.learning_rate * (((1.0 - error) / error).ln() + (num_classes - 1.0).ln());
+            println!("Round {}: weighted error: {}, say: {}", round, error, say);
+            for (weight, &miss) in weights.iter_mut().zip(misses.iter()) {
+                if miss {
+                    *weight *= say.exp();
+                }
+            }
+            let total = weights.iter().sum::<f64>();
+            for weight in weights.iter_mut() {
+                *weight /= total;
+            }
+            self.learners.push((learner, say));
+        }
+
+        Ok(())
+    }
+
+    pub fn num_learners(&self) -> usize {
+        self.learners.len()
+    }
+
+    // Returns the total say of the learners voting for each class, in class order
+    fn scores(&self, sample: &[Numeric]) -> Vec<f64> {
+        let mut scores = vec![0.0; self.classes.len()];
+        for (learner, say) in self.learners.iter() {
+            let label = learner.label(sample);
+            if let Some(position) = self
+                .classes
+                .iter()
+                .position(|class| (class - label).abs() < NUMERIC_DIGIT_PRECISION)
+            {
+                scores[position] += say;
+            }
+        }
+        scores
+    }
+}
+
+pub struct AdaBoostTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for AdaBoostTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = AdaBoost::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built AdaBoost with {} {} learners",
+            model.num_learners(),
+            model.base
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -0,0 +1,140 @@
+// autoencoder.rs
+
+//! This file implements an autoencoder: a feed-forward network trained to reproduce its inputs
+//! through a narrow middle layer (the bottleneck). The encoder half maps the features to the
+//! bottleneck and the decoder half mirrors it back to the features, the reconstruction is linear
+//! and trained on the squared error. After training, the encoder gives a compressed
+//! representation of the features, which can replace them in the table or initialize the hidden
+//! layers of a supervised network.
+
+use super::mlp::{format_layer_sizes, parse_layer_sizes, Activation, GradientDescent, Layer, Loss, Network};
+
+use crate::types::Numeric;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+#[derive(Debug)]
+pub struct Autoencoder {
+    // Sizes of the encoder layers before the bottleneck, mirrored by the decoder
+    pub hidden_layers: Vec<usize>,
+    pub encoding_size: usize,
+    pub activation: Activation,
+    pub settings: GradientDescent,
+    pub seed: u64,
+    network: Network,
+}
+
+impl Default for Autoencoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Autoencoder {
+    pub fn new() -> Self {
+        Self {
+            hidden_layers: Vec::new(),
+            encoding_size: 2,
+            activation: Activation::Tanh,
+            settings: GradientDescent::default(),
+            seed: 0,
+            network: Network::default(),
+        }
+    }
+
+    pub fn get_hyperparameters(&self) -> HashMap<String, String> {
+        let mut ret = self.settings.get_hyperparameters();
+        ret.insert("hidden_layers".into(), format_layer_sizes(&self.hidden_layers));
+        ret.insert("encoding_size".into(), self.encoding_size.to_string());
+        ret.insert("activation".into(), self.activation.to_string());
+        ret.insert("seed".into(), self.seed.to_string());
+        ret
+    }
+
+    pub fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            if self.settings.set_hyperparameter(key, val)? {
+                continue;
+            }
+            match key.as_str() {
+                "hidden_layers" => {
+                    self.hidden_layers = parse_layer_sizes(val)?;
+                }
+                "encoding_size" => {
+                    self.encoding_size = val.parse::<usize>()?;
+                    if self.encoding_size == 0 {
+                        return Err("The encoding needs at least one unit!".into());
+                    }
+                }
+                "activation" => {
+                    self.activation = Activation::from_name(val)?;
+                }
+                "seed" => {
+                    self.seed = val.parse::<u64>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Trains the network to reconstruct the rows, logging the reconstruction error of every
+    /// epoch. Must be called after the hyperparameters are set.
+    pub fn fit(&mut self, rows: &[Vec<Numeric>]) -> Result<(), Box<dyn Error>> {
+        if rows.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+
+        // Layer sizes go down to the bottleneck and back up to the features
+        let num_features = rows[0].len();
+        let mut sizes = vec![num_features];
+        sizes.extend(self.hidden_layers.iter());
+        sizes.push(self.encoding_size);
+        sizes.extend(self.hidden_layers.iter().rev());
+        sizes.push(num_features);
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.network = Network::new(&sizes, self.activation, Activation::Linear, &mut rng);
+        self.network.fit(
+            rows,
+            rows,
+            Loss::SquaredError,
+            &self.settings,
+            &mut rng,
+            "reconstruction error",
+        );
+
+        Ok(())
+    }
+
+    /// Returns the layers mapping the features to the bottleneck
+    pub fn encoder(&self) -> &[Layer] {
+        &self.network.layers[..self.hidden_layers.len() + 1]
+    }
+
+    /// Returns the bottleneck values of a row of features
+    pub fn encode(&self, row: &[Numeric]) -> Vec<Numeric> {
+        self.encoder()
+            .iter()
+            .fold(row.to_vec(), |values, layer| layer.forward(&values))
+    }
+
+    /// Returns the mean squared error of reconstructing the rows
+    pub fn reconstruction_error(&self, rows: &[Vec<Numeric>]) -> f64 {
+        rows.iter()
+            .map(|row| {
+                self.network
+                    .forward(row)
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(output, value)| (output - value).powi(2))
+                    .sum::<f64>()
+            })
+            .sum::<f64>()
+            / rows.len() as f64
+    }
+}
@@ -0,0 +1,822 @@
+// decision_tree.rs
+
+//! This file implements a decision tree classifier in the style of ID3/C4.5. Nodes are split on the
+//! feature with the best gain ratio among the features with at least average information gain.
+//! Numeric features are split in two at the threshold with the largest information gain, while
+//! categorical features (columns with a value map in their metadata) get one branch per value.
+//! The tree can be pruned with reduced-error pruning: part of the training data is held out, and
+//! a subtree is replaced by a leaf whenever that doesn't increase the error on the held-out data.
+//! With sample weights the class counts behind the entropies, the leaf answers and the leaf
+//! errors add up the weights of the examples instead of counting them.
+
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::data::column::ColumnInfo;
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+const DEFAULT_VALIDATION_FRACTION: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Pruning {
+    None,
+    ReducedError,
+}
+
+impl Pruning {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "none" => Ok(Self::None),
+            "reduced-error" => Ok(Self::ReducedError),
+            _ => Err(format!("Unsupported pruning strategy: {}", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for Pruning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::None => "none",
+            Self::ReducedError => "reduced-error",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeNode {
+    // Answer of the node when it is a leaf or when a sample can't follow any of its branches
+    pub value: Numeric,
+    // Class probabilities of the training examples in the node, empty for regression
+    pub distribution: ClassProbabilities,
+    pub samples: usize,
+    // Training error of the node as a leaf, misclassified examples for classification and the sum
+    // of squared errors for regression
+    pub error: Numeric,
+    pub split: Option<Split>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum Split {
+    // Samples with a value at or below the threshold go to the first branch
+    Threshold {
+        column: usize,
+        threshold: Numeric,
+        below: Box<TreeNode>,
+        above: Box<TreeNode>,
+    },
+    // One branch per categorical value seen in the training examples of the node
+    Categories {
+        column: usize,
+        branches: Vec<(Numeric, TreeNode)>,
+    },
+}
+
+impl TreeNode {
+    pub fn leaf(
+        value: Numeric,
+        distribution: ClassProbabilities,
+        samples: usize,
+        error: Numeric,
+    ) -> Self {
+        Self {
+            value,
+            distribution,
+            samples,
+            error,
+            split: None,
+        }
+    }
+
+    /// Returns the node the sample ends up in, which is a leaf unless the sample has a categorical
+    /// value no branch was grown for
+    pub fn find(&self, sample: &[Numeric]) -> &TreeNode {
+        match &self.split {
+            None => self,
+            Some(Split::Threshold {
+                column,
+                threshold,
+                below,
+                above,
+            }) => {
+                if sample[*column] <= *threshold {
+                    below.find(sample)
+                } else {
+                    above.find(sample)
+                }
+            }
+            Some(Split::Categories { column, branches }) => {
+                match branches
+                    .iter()
+                    .find(|(value, _)| (value - sample[*column]).abs() < NUMERIC_DIGIT_PRECISION)
+                {
+                    Some((_, child)) => child.find(sample),
+                    None => self,
+                }
+            }
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match &self.split {
+            None => 0,
+            Some(Split::Threshold { below, above, .. }) => 1 + below.depth().max(above.depth()),
+            Some(Split::Categories { branches, .. }) => {
+                1 + branches.iter().map(|(_, child)| child.depth()).max().unwrap_or(0)
+            }
+        }
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        match &self.split {
+            None => 1,
+            Some(Split::Threshold { below, above, .. }) => below.num_leaves() + above.num_leaves(),
+            Some(Split::Categories { branches, .. }) => {
+                branches.iter().map(|(_, child)| child.num_leaves()).sum()
+            }
+        }
+    }
+
+    /// Returns the training error of the leaves under the node
+    pub fn subtree_error(&self) -> Numeric {
+        match &self.split {
+            None => self.error,
+            _ => self.children().iter().map(|child| child.subtree_error()).sum(),
+        }
+    }
+
+    pub fn children(&self) -> Vec<&TreeNode> {
+        match &self.split {
+            None => Vec::new(),
+            Some(Split::Threshold { below, above, .. }) => vec![below, above],
+            Some(Split::Categories { branches, .. }) => {
+                branches.iter().map(|(_, child)| child).collect()
+            }
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut TreeNode> {
+        match &mut self.split {
+            None => Vec::new(),
+            Some(Split::Threshold { below, above, .. }) => vec![below, above],
+            Some(Split::Categories { branches, .. }) => {
+                branches.iter_mut().map(|(_, child)| child).collect()
+            }
+        }
+    }
+
+    /// Writes the tree one test per line, indented by depth. Columns and categorical values are
+    /// named with the column information when it is available.
+    pub fn describe(&self, columns: &[ColumnInfo], label_index: usize) -> String {
+        let mut lines = Vec::new();
+        self.describe_lines(columns, label_index, 0, &mut lines);
+        lines.join("\n")
+    }
+
+    fn describe_lines(
+        &self,
+        columns: &[ColumnInfo],
+        label_index: usize,
+        depth: usize,
+        lines: &mut Vec<String>,
+    ) {
+        let indent = "|   ".repeat(depth);
+        match &self.split {
+            None => {
+                let answer = if self.distribution.is_empty() {
+                    format!("value: {}", self.value)
+                } else {
+                    format!("class: {}", value_name(columns, label_index, self.value))
+                };
+                lines.push(format!("{}{} ({})", indent, answer, self.samples));
+            }
+            Some(Split::Threshold {
+                column,
+                threshold,
+                below,
+                above,
+            }) => {
+                let name = column_name(columns, *column);
+                lines.push(format!("{}{} <= {}", indent, name, threshold));
+                below.describe_lines(columns, label_index, depth + 1, lines);
+                lines.push(format!("{}{} > {}", indent, name, threshold));
+                above.describe_lines(columns, label_index, depth + 1, lines);
+            }
+            Some(Split::Categories { column, branches }) => {
+                let name = column_name(columns, *column);
+                for (value, child) in branches.iter() {
+                    lines.push(format!(
+                        "{}{} = {}",
+                        indent,
+                        name,
+                        value_name(columns, *column, *value)
+                    ));
+                    child.describe_lines(columns, label_index, depth + 1, lines);
+                }
+            }
+        }
+    }
+}
+
+fn column_name(columns: &[ColumnInfo], column: usize) -> String {
+    columns
+        .get(column)
+        .and_then(|info| info.name.clone())
+        .unwrap_or_else(|| format!("x[{}]", column))
+}
+
+fn value_name(columns: &[ColumnInfo], column: usize, value: Numeric) -> String {
+    columns
+        .get(column)
+        .and_then(|info| info.value_name(value))
+        .map(|name| name.to_owned())
+        .unwrap_or_else(|| value.to_string())
+}
+
+/// Returns the feature columns a node may split on. When a maximum number of features is given,
+/// a different random subset of that size is drawn for every node, as random forests do.
+pub fn split_columns(
+    num_columns: usize,
+    label_index: usize,
+    max_features: Option<usize>,
+) -> Vec<usize> {
+    let columns = (0..num_columns)
+        .filter(|&column| column != label_index)
+        .collect::<Vec<usize>>();
+    match max_features {
+        Some(max_features) if max_features < columns.len() => columns
+            .choose_multiple(&mut rand::thread_rng(), max_features.max(1))
+            .copied()
+            .collect(),
+        _ => columns,
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DecisionTree {
+    pub label_index: usize,
+    pub max_depth: Option<usize>,
+    pub min_samples_split: usize,
+    pub pruning: Pruning,
+    pub validation_fraction: f64,
+    // Number of features considered at each split, all of them when missing
+    pub max_features: Option<usize>,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    root: TreeNode,
+}
+
+impl Model for DecisionTree {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        self.root.find(sample).value
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        self.root.find(sample).value
+    }
+
+    // Returns the class frequencies of the training examples in the node the sample ends up in
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        self.root.find(sample).distribution.clone()
+    }
+
+    fn type_id(&self) -> &'static str {
+        "DecisionTree"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "max_depth".into(),
+                self.max_depth
+                    .map_or("none".to_string(), |max_depth| max_depth.to_string()),
+            ),
+            ("min_samples_split".into(), self.min_samples_split.to_string()),
+            ("pruning".into(), self.pruning.to_string()),
+            ("validation_fraction".into(), self.validation_fraction.to_string()),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "max_depth" => {
+                    self.max_depth = match val.as_str() {
+                        "none" => None,
+                        _ => Some(val.parse::<usize>()?),
+                    };
+                }
+                "min_samples_split" => {
+                    self.min_samples_split = val.parse::<usize>()?;
+                }
+                "pruning" => {
+                    self.pruning = Pruning::from_name(val)?;
+                }
+                "validation_fraction" => {
+                    let validation_fraction = val.parse::<f64>()?;
+                    if !(0.0..1.0).contains(&validation_fraction) {
+                        return Err("The validation fraction must be in [0, 1)!".into());
+                    }
+                    self.validation_fraction = validation_fraction;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DecisionTree {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            max_depth: None,
+            min_samples_split: 2,
+            pruning: Pruning::None,
+            validation_fraction: DEFAULT_VALIDATION_FRACTION,
+            max_features: None,
+            classes: Vec::new(),
+            root: TreeNode::leaf(0.0, Vec::new(), 0, 0.0),
+        }
+    }
+
+    /// Grows the tree on the training values and prunes it when pruning is enabled. Must be called
+    /// after the hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.fit_weighted(training_values, None, context)
+    }
+
+    /// Same as fit, but every training value counts as much as its sample weight
+    pub fn fit_weighted(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        sample_weights: Option<&[Numeric]>,
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        if let Some(sample_weights) = sample_weights {
+            if sample_weights.len() != training_values.len() {
+                return Err("Expected one sample weight per training value!".into());
+            }
+            if sample_weights.iter().any(|&weight| weight < 0.0 || !weight.is_finite()) {
+                return Err("Sample weights must be finite and not negative!".into());
+            }
+        }
+        self.classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+
+        // Hold out part of the training values to prune the tree with
+        let mut indexes = (0..training_values.len()).collect::<Vec<usize>>();
+        let mut validation_indexes = Vec::new();
+        if self.pruning == Pruning::ReducedError {
+            if training_values.len() < 2 {
+                return Err("Not enough training values to hold out a pruning set!".into());
+            }
+            indexes.shuffle(&mut rand::thread_rng());
+            let num_validation = ((training_values.len() as f64 * self.validation_fraction)
+                .round() as usize)
+                .clamp(1, training_values.len() - 1);
+            validation_indexes = indexes.split_off(training_values.len() - num_validation);
+        }
+
+        let grower = Grower::new(self, training_values, sample_weights, context);
+        self.root = grower.grow(indexes, 0);
+
+        if self.pruning == Pruning::ReducedError {
+            let num_leaves = self.root.num_leaves();
+            let validation_set = validation_indexes
+                .iter()
+                .map(|&idx| training_values[idx].as_ref())
+                .collect::<Vec<_>>();
+            let errors = prune(&mut self.root, &validation_set, self.label_index);
+            println!(
+                "Reduced-error pruning: {} -> {} leaves, {} errors on {} held-out samples",
+                num_leaves,
+                self.root.num_leaves(),
+                errors,
+                validation_set.len()
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn root(&self) -> &TreeNode {
+        &self.root
+    }
+}
+
+// Candidate split of a node, the threshold is missing for categorical splits
+struct Candidate {
+    column: usize,
+    threshold: Option<Numeric>,
+    gain: f64,
+    gain_ratio: f64,
+}
+
+// Holds the training data while the tree grows
+struct Grower<'a> {
+    training_values: &'a [Box<[Numeric]>],
+    label_index: usize,
+    max_depth: Option<usize>,
+    min_samples_split: usize,
+    max_features: Option<usize>,
+    classes: &'a [Numeric],
+    categorical: Vec<bool>,
+    // Position in the classes of the label of each training value
+    labels: Vec<usize>,
+    // Weight of each training value in the class counts
+    weights: Vec<f64>,
+}
+
+impl<'a> Grower<'a> {
+    fn new(
+        tree: &'a DecisionTree,
+        training_values: &'a [Box<[Numeric]>],
+        sample_weights: Option<&[Numeric]>,
+        context: &TrainingContext,
+    ) -> Self {
+        let class_positions = tree
+            .classes
+            .iter()
+            .enumerate()
+            .map(|(position, class)| ((class / NUMERIC_DIGIT_PRECISION) as i64, position))
+            .collect::<HashMap<i64, usize>>();
+        let labels = training_values
+            .iter()
+            .map(|sample| {
+                let key = (sample[tree.label_index] / NUMERIC_DIGIT_PRECISION) as i64;
+                class_positions.get(&key).copied().unwrap_or(0)
+            })
+            .collect();
+
+        Self {
+            training_values,
+            label_index: tree.label_index,
+            max_depth: tree.max_depth,
+            min_samples_split: tree.min_samples_split,
+            max_features: tree.max_features,
+            classes: &tree.classes,
+            categorical: context.categorical_columns(),
+            labels,
+            weights: sample_weights.map_or_else(
+                || vec![1.0; training_values.len()],
+                |sample_weights| sample_weights.to_vec(),
+            ),
+        }
+    }
+
+    // Returns the total weight of the examples of every class
+    fn counts(&self, indexes: &[usize]) -> Vec<f64> {
+        let mut counts = vec![0.0; self.classes.len()];
+        for &idx in indexes.iter() {
+            counts[self.labels[idx]] += self.weights[idx];
+        }
+        counts
+    }
+
+    fn leaf(&self, counts: &[f64], samples: usize) -> TreeNode {
+        let total = counts.iter().sum::<f64>();
+
+        // The most common class is the answer, ties go to the smallest class
+        let mode = counts
+            .iter()
+            .enumerate()
+            .fold(0, |best, (position, &count)| {
+                if count > counts[best] {
+                    position
+                } else {
+                    best
+                }
+            });
+        let distribution = self
+            .classes
+            .iter()
+            .zip(counts.iter())
+            .map(|(&class, &count)| {
+                let share = if total > 0.0 { count / total } else { 0.0 };
+                (class, share)
+            })
+            .collect();
+
+        TreeNode::leaf(self.classes[mode], distribution, samples, total - counts[mode])
+    }
+
+    fn grow(&self, indexes: Vec<usize>, depth: usize) -> TreeNode {
+        let counts = self.counts(&indexes);
+        let mut node = self.leaf(&counts, indexes.len());
+
+        // Stop on pure nodes, small nodes or at the maximum depth
+        let is_pure = counts.iter().filter(|&&count| count > 0.0).count() <= 1;
+        if is_pure
+            || indexes.len() < self.min_samples_split
+            || self.max_depth.is_some_and(|max_depth| depth >= max_depth)
+        {
+            return node;
+        }
+
+        // Find the best split of each feature
+        let parent_entropy = entropy(&counts);
+        let num_columns = self.training_values[0].len();
+        let candidates = split_columns(num_columns, self.label_index, self.max_features)
+            .into_iter()
+            .filter_map(|column| {
+                if self.categorical.get(column).copied().unwrap_or(false) {
+                    self.categorical_candidate(&indexes, column, parent_entropy)
+                } else {
+                    self.threshold_candidate(&indexes, column, parent_entropy)
+                }
+            })
+            .filter(|candidate| candidate.gain > NUMERIC_DIGIT_PRECISION)
+            .collect::<Vec<Candidate>>();
+        if candidates.is_empty() {
+            return node;
+        }
+
+        // Like C4.5, only features with at least average gain compete on gain ratio, so a
+        // feature with a tiny split information doesn't win with a useless split
+        let average_gain =
+            candidates.iter().map(|candidate| candidate.gain).sum::<f64>() / candidates.len() as f64;
+        let best = candidates
+            .iter()
+            .filter(|candidate| candidate.gain >= average_gain - NUMERIC_DIGIT_PRECISION)
+            .max_by(|x, y| x.gain_ratio.total_cmp(&y.gain_ratio))
+            .expect("No split found!");
+
+        node.split = Some(match best.threshold {
+            Some(threshold) => {
+                let (below, above): (Vec<usize>, Vec<usize>) = indexes
+                    .iter()
+                    .partition(|&&idx| self.training_values[idx][best.column] <= threshold);
+                Split::Threshold {
+                    column: best.column,
+                    threshold,
+                    below: Box::new(self.grow(below, depth + 1)),
+                    above: Box::new(self.grow(above, depth + 1)),
+                }
+            }
+            None => Split::Categories {
+                column: best.column,
+                branches: self
+                    .partition_by_value(&indexes, best.column)
+                    .into_iter()
+                    .map(|(value, branch)| (value, self.grow(branch, depth + 1)))
+                    .collect(),
+            },
+        });
+        node
+    }
+
+    // Groups the examples by their value in the column, sorted by value
+    fn partition_by_value(&self, indexes: &[usize], column: usize) -> Vec<(Numeric, Vec<usize>)> {
+        let mut partitions: Vec<(Numeric, Vec<usize>)> = Vec::new();
+        for &idx in indexes.iter() {
+            let value = self.training_values[idx][column];
+            match partitions
+                .iter_mut()
+                .find(|(other, _)| (other - value).abs() < NUMERIC_DIGIT_PRECISION)
+            {
+                Some((_, partition)) => partition.push(idx),
+                None => partitions.push((value, vec![idx])),
+            }
+        }
+        partitions.sort_by(|(x, _), (y, _)| x.total_cmp(y));
+        partitions
+    }
+
+    fn categorical_candidate(
+        &self,
+        indexes: &[usize],
+        column: usize,
+        parent_entropy: f64,
+    ) -> Option<Candidate> {
+        let partitions = self.partition_by_value(indexes, column);
+        if partitions.len() < 2 {
+            return None;
+        }
+        let partition_counts = partitions
+            .iter()
+            .map(|(_, partition)| self.counts(partition))
+            .collect::<Vec<Vec<f64>>>();
+        let (gain, gain_ratio) = gain_and_ratio(parent_entropy, &partition_counts);
+        Some(Candidate {
+            column,
+            threshold: None,
+            gain,
+            gain_ratio,
+        })
+    }
+
+    // Tries every threshold between consecutive values and keeps the one with the largest gain
+    fn threshold_candidate(
+        &self,
+        indexes: &[usize],
+        column: usize,
+        parent_entropy: f64,
+    ) -> Option<Candidate> {
+        let mut sorted = indexes.to_vec();
+        sorted.sort_by(|&x, &y| {
+            self.training_values[x][column].total_cmp(&self.training_values[y][column])
+        });
+
+        let total_counts = self.counts(indexes);
+        let mut below_counts = vec![0.0; self.classes.len()];
+        let mut best: Option<Candidate> = None;
+        for position in 0..sorted.len() - 1 {
+            below_counts[self.labels[sorted[position]]] += self.weights[sorted[position]];
+            let value = self.training_values[sorted[position]][column];
+            let next_value = self.training_values[sorted[position + 1]][column];
+            if (next_value - value).abs() < NUMERIC_DIGIT_PRECISION {
+                continue;
+            }
+
+            let above_counts = total_counts
+                .iter()
+                .zip(below_counts.iter())
+                .map(|(total, below)| (total - below).max(0.0))
+                .collect::<Vec<f64>>();
+            let (gain, gain_ratio) =
+                gain_and_ratio(parent_entropy, &[below_counts.clone(), above_counts]);
+            if best.as_ref().is_none_or(|best| gain > best.gain) {
+                best = Some(Candidate {
+                    column,
+                    threshold: Some((value + next_value) / 2.0),
+                    gain,
+                    gain_ratio,
+                });
+            }
+        }
+        best
+    }
+}
+
+// Entropy in bits of a class distribution given by its (weighted) counts
+fn entropy(counts: &[f64]) -> f64 {
+    let total = counts.iter().sum::<f64>();
+    counts
+        .iter()
+        .filter(|&&count| count > 0.0)
+        .map(|&count| {
+            let p = count / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// Information gain and gain ratio of splitting a node into partitions with the given class counts
+fn gain_and_ratio(parent_entropy: f64, partition_counts: &[Vec<f64>]) -> (f64, f64) {
+    let sizes = partition_counts
+        .iter()
+        .map(|counts| counts.iter().sum::<f64>())
+        .collect::<Vec<f64>>();
+    let total = sizes.iter().sum::<f64>();
+
+    let children_entropy = partition_counts
+        .iter()
+        .zip(sizes.iter())
+        .map(|(counts, size)| size / total * entropy(counts))
+        .sum::<f64>();
+    let gain = parent_entropy - children_entropy;
+
+    let split_information = sizes
+        .iter()
+        .filter(|&&size| size > 0.0)
+        .map(|size| -(size / total) * (size / total).log2())
+        .sum::<f64>();
+    let gain_ratio = if split_information > 0.0 {
+        gain / split_information
+    } else {
+        0.0
+    };
+
+    (gain, gain_ratio)
+}
+
+// Replaces subtrees with leaves, bottom up, when the leaf makes no more errors on the held-out
+// samples that reach the node than the subtree does. Returns the errors of the (pruned) node.
+fn prune(node: &mut TreeNode, validation_set: &[&[Numeric]], label_index: usize) -> usize {
+    let is_error = |value: Numeric, sample: &[Numeric]| {
+        (sample[label_index] - value).abs() >= NUMERIC_DIGIT_PRECISION
+    };
+    let leaf_errors = validation_set
+        .iter()
+        .filter(|sample| is_error(node.value, sample))
+        .count();
+
+    let subtree_errors = match node.split.as_mut() {
+        None => return leaf_errors,
+        Some(Split::Threshold {
+            column,
+            threshold,
+            below,
+            above,
+        }) => {
+            let (below_set, above_set): (Vec<_>, Vec<_>) = validation_set
+                .iter()
+                .partition(|sample| sample[*column] <= *threshold);
+            prune(below, &below_set, label_index) + prune(above, &above_set, label_index)
+        }
+        Some(Split::Categories { column, branches }) => {
+            let mut errors = 0;
+            let mut unmatched = validation_set.to_vec();
+            for (value, child) in branches.iter_mut() {
+                let (branch_set, rest): (Vec<_>, Vec<_>) = unmatched
+                    .into_iter()
+                    .partition(|sample| (sample[*column] - *value).abs() < NUMERIC_DIGIT_PRECISION);
+                errors += prune(child, &branch_set, label_index);
+                unmatched = rest;
+            }
+            // Samples with a value without a branch are answered by the node itself
+            errors
+                + unmatched
+                    .iter()
+                    .filter(|sample| is_error(node.value, sample))
+                    .count()
+        }
+    };
+
+    if leaf_errors <= subtree_errors {
+        node.split = None;
+        leaf_errors
+    } else {
+        subtree_errors
+    }
+}
+
+pub struct DecisionTreeTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for DecisionTreeTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = DecisionTree::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit_weighted(training_values, sample_weights, &self.context)?;
+
+        println!(
+            "Built decision tree with {} leaves and depth {}:\n{}",
+            model.root().num_leaves(),
+            model.root().depth(),
+            model.root().describe(&self.context.columns, target_value_idx)
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -0,0 +1,440 @@
+// distance.rs
+
+//! This file implements the distance metrics used by the k-nearest neighbor learners to find the
+//! examples closest to a sample. Every metric ignores the label column and can scale the
+//! contribution of each feature with an optional weight. The value difference metric needs the
+//! class distribution of every feature value, so it has to be fitted on the training data.
+//! The heterogeneous value difference metric (HVDM) uses the column metadata to tell categorical
+//! columns apart from numeric ones: numeric differences are normalized by four standard deviations
+//! and categorical values are compared using VDM or the overlap (0 if equal, 1 otherwise).
+//! Hamming and VDM only make sense for categorical values, they refuse data with numeric features.
+
+use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DistanceMetric {
+    SquaredEuclidean,
+    Euclidean,
+    Manhattan,
+    Minkowski,
+    Chebyshev,
+    Cosine,
+    Hamming,
+    ValueDifference,
+    Heterogeneous,
+}
+
+impl DistanceMetric {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "squared-euclidean" => Ok(Self::SquaredEuclidean),
+            "euclidean" => Ok(Self::Euclidean),
+            "manhattan" => Ok(Self::Manhattan),
+            "minkowski" => Ok(Self::Minkowski),
+            "chebyshev" => Ok(Self::Chebyshev),
+            "cosine" => Ok(Self::Cosine),
+            "hamming" => Ok(Self::Hamming),
+            "vdm" => Ok(Self::ValueDifference),
+            "hvdm" => Ok(Self::Heterogeneous),
+            _ => Err(format!("Unsupported distance metric: {}", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for DistanceMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::SquaredEuclidean => "squared-euclidean",
+            Self::Euclidean => "euclidean",
+            Self::Manhattan => "manhattan",
+            Self::Minkowski => "minkowski",
+            Self::Chebyshev => "chebyshev",
+            Self::Cosine => "cosine",
+            Self::Hamming => "hamming",
+            Self::ValueDifference => "vdm",
+            Self::Heterogeneous => "hvdm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CategoricalDistance {
+    ValueDifference,
+    Overlap,
+}
+
+impl CategoricalDistance {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "vdm" => Ok(Self::ValueDifference),
+            "overlap" => Ok(Self::Overlap),
+            _ => Err(format!("Unsupported categorical distance: {}", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for CategoricalDistance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ValueDifference => write!(f, "vdm"),
+            Self::Overlap => write!(f, "overlap"),
+        }
+    }
+}
+
+/// Conditional class probabilities of each value of each feature column, used by the value
+/// difference metric
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValueDifferenceTable {
+    // Probability of each class given that the feature has a value, indexed by column
+    conditional: Vec<HashMap<i64, Vec<f64>>>,
+    // Probability of each class, used for feature values not seen while fitting
+    prior: Vec<f64>,
+}
+
+impl ValueDifferenceTable {
+    pub fn new(training_values: &[Box<[Numeric]>], label_index: usize) -> Self {
+        let to_key = |value: Numeric| (value / NUMERIC_DIGIT_PRECISION) as i64;
+
+        // Give every class a position in the probability vectors
+        let mut classes = training_values
+            .iter()
+            .map(|sample| to_key(sample[label_index]))
+            .collect::<Vec<i64>>();
+        classes.sort();
+        classes.dedup();
+        let class_position = |value: Numeric| {
+            classes
+                .binary_search(&to_key(value))
+                .expect("Class not found!")
+        };
+
+        // Count the number of times each feature value shows up with each class
+        let num_columns = training_values.first().map_or(0, |sample| sample.len());
+        let mut conditional = vec![HashMap::new(); num_columns];
+        let mut prior = vec![0.0; classes.len()];
+        for sample in training_values.iter() {
+            let class = class_position(sample[label_index]);
+            prior[class] += 1.0;
+            for (column, &value) in conditional.iter_mut().zip(sample.iter()) {
+                let counts = column
+                    .entry(to_key(value))
+                    .or_insert(vec![0.0; classes.len()]);
+                counts[class] += 1.0;
+            }
+        }
+
+        // Normalize the counts into probabilities
+        for counts in conditional
+            .iter_mut()
+            .flat_map(|column| column.values_mut())
+            .chain(std::iter::once(&mut prior))
+        {
+            let total = counts.iter().sum::<f64>();
+            counts.iter_mut().for_each(|count| *count /= total);
+        }
+
+        Self { conditional, prior }
+    }
+
+    /// Squared difference between the class distributions of two values of a feature column
+    pub fn difference(&self, column: usize, x: Numeric, y: Numeric) -> f64 {
+        let x = self.probabilities(column, x);
+        let y = self.probabilities(column, y);
+        x.iter()
+            .zip(y.iter())
+            .fold(0.0, |acc, (p, q)| acc + (p - q) * (p - q))
+    }
+
+    fn probabilities(&self, column: usize, value: Numeric) -> &[f64] {
+        self.conditional[column]
+            .get(&((value / NUMERIC_DIGIT_PRECISION) as i64))
+            .map_or(self.prior.as_slice(), |probabilities| {
+                probabilities.as_slice()
+            })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Distance {
+    pub metric: DistanceMetric,
+    pub minkowski_p: f64,
+    // Weight of each feature, in column order skipping the label column
+    pub weights: Option<Vec<f64>>,
+    pub categorical_distance: CategoricalDistance,
+    // Which columns hold categorical values, indexed by column
+    categorical: Vec<bool>,
+    // Standard deviation of each column, used to normalize numeric differences in HVDM
+    deviations: Vec<f64>,
+    value_difference: Option<ValueDifferenceTable>,
+}
+
+impl Default for Distance {
+    fn default() -> Self {
+        Self {
+            metric: DistanceMetric::SquaredEuclidean,
+            minkowski_p: 2.0,
+            weights: None,
+            categorical_distance: CategoricalDistance::ValueDifference,
+            categorical: Vec::new(),
+            deviations: Vec::new(),
+            value_difference: None,
+        }
+    }
+}
+
+impl Distance {
+    /// Checks the metric can be used with the training data and calculates the statistics the
+    /// metric needs. Columns not marked as categorical are treated as numeric.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        label_index: usize,
+        categorical: &[bool],
+    ) -> Result<(), Box<dyn Error>> {
+        let num_features = training_values
+            .first()
+            .ok_or("Empty training set given!")?
+            .len()
+            - 1;
+        if let Some(weights) = self.weights.as_ref() {
+            if weights.len() != num_features {
+                return Err(format!(
+                    "Got {} feature weights for {} features!",
+                    weights.len(),
+                    num_features
+                )
+                .into());
+            }
+        }
+        if self.metric == DistanceMetric::Minkowski && self.minkowski_p < 1.0 {
+            return Err("Minkowski distance needs p to be at least 1!".into());
+        }
+
+        let num_columns = num_features + 1;
+        self.categorical = (0..num_columns)
+            .map(|idx| categorical.get(idx).copied().unwrap_or(false))
+            .collect();
+        if matches!(
+            self.metric,
+            DistanceMetric::Hamming | DistanceMetric::ValueDifference
+        ) {
+            if let Some(column) =
+                (0..num_columns).find(|&idx| idx != label_index && !self.categorical[idx])
+            {
+                return Err(format!(
+                    "The {} distance only compares categorical features but column {} is \
+                     numeric, use hvdm to mix numeric and categorical features!",
+                    self.metric, column
+                )
+                .into());
+            }
+        }
+
+        self.value_difference = match (self.metric, self.categorical_distance) {
+            (DistanceMetric::ValueDifference, _)
+            | (DistanceMetric::Heterogeneous, CategoricalDistance::ValueDifference) => {
+                Some(ValueDifferenceTable::new(training_values, label_index))
+            }
+            _ => None,
+        };
+
+        // Calculate the sample standard deviation of every column
+        self.deviations = match self.metric {
+            DistanceMetric::Heterogeneous => (0..num_columns)
+                .map(|idx| {
+                    let count = training_values.len() as f64;
+                    let mean = training_values.iter().fold(0.0, |acc, x| acc + x[idx]) / count;
+                    let variance = training_values
+                        .iter()
+                        .fold(0.0, |acc, x| acc + (x[idx] - mean) * (x[idx] - mean))
+                        / (count - 1.0).max(1.0);
+                    variance.sqrt()
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(())
+    }
+
+    /// Calculates the distance between two samples ignoring the label column
+    pub fn between(&self, sample: &[Numeric], example: &[Numeric], label_index: usize) -> Numeric {
+        // Iterate over (column index, weight, sample value, example value) of every feature
+        let features = sample
+            .iter()
+            .zip(example.iter())
+            .enumerate()
+            .filter(|&(idx, _)| idx != label_index)
+            .enumerate()
+            .map(|(feature, (column, (x, y)))| {
+                let weight = self
+                    .weights
+                    .as_ref()
+                    .map_or(1.0, |weights| weights[feature]);
+                (column, weight, *x, *y)
+            });
+
+        match self.metric {
+            DistanceMetric::SquaredEuclidean => {
+                features.fold(0.0, |acc, (_, w, x, y)| acc + w * (x - y) * (x - y))
+            }
+            DistanceMetric::Euclidean => features
+                .fold(0.0, |acc, (_, w, x, y)| acc + w * (x - y) * (x - y))
+                .sqrt(),
+            DistanceMetric::Manhattan => {
+                features.fold(0.0, |acc, (_, w, x, y)| acc + w * (x - y).abs())
+            }
+            DistanceMetric::Minkowski => features
+                .fold(0.0, |acc, (_, w, x, y)| {
+                    acc + w * (x - y).abs().powf(self.minkowski_p)
+                })
+                .powf(1.0 / self.minkowski_p),
+            DistanceMetric::Chebyshev => features.fold(0.0, |acc, (_, w, x, y)| {
+                Numeric::max(acc, w * (x - y).abs())
+            }),
+            DistanceMetric::Cosine => {
+                let (dot, x_norm, y_norm) =
+                    features.fold((0.0, 0.0, 0.0), |(dot, x_norm, y_norm), (_, w, x, y)| {
+                        (dot + w * x * y, x_norm + w * x * x, y_norm + w * y * y)
+                    });
+                if x_norm == 0.0 && y_norm == 0.0 {
+                    0.0
+                } else if x_norm == 0.0 || y_norm == 0.0 {
+                    1.0
+                } else {
+                    1.0 - dot / (x_norm.sqrt() * y_norm.sqrt())
+                }
+            }
+            DistanceMetric::Hamming => features.fold(0.0, |acc, (_, w, x, y)| {
+                if (x - y).abs() > NUMERIC_DIGIT_PRECISION {
+                    acc + w
+                } else {
+                    acc
+                }
+            }),
+            DistanceMetric::ValueDifference => {
+                let table = self
+                    .value_difference
+                    .as_ref()
+                    .expect("Value difference metric was not fitted!");
+                features
+                    .fold(0.0, |acc, (column, w, x, y)| {
+                        acc + w * table.difference(column, x, y)
+                    })
+                    .sqrt()
+            }
+            DistanceMetric::Heterogeneous => features
+                .fold(0.0, |acc, (column, w, x, y)| {
+                    acc + w * self.heterogeneous_difference(column, x, y)
+                })
+                .sqrt(),
+        }
+    }
+
+    /// True if the distance only grows when the difference along any single feature grows, which
+    /// is what a KD-tree needs to prune its search
+    pub fn is_coordinate_wise(&self) -> bool {
+        matches!(
+            self.metric,
+            DistanceMetric::SquaredEuclidean
+                | DistanceMetric::Euclidean
+                | DistanceMetric::Manhattan
+                | DistanceMetric::Minkowski
+                | DistanceMetric::Chebyshev
+        )
+    }
+
+    /// True if the distance (after `to_metric`) follows the triangle inequality
+    pub fn is_metric(&self) -> bool {
+        self.metric != DistanceMetric::Cosine
+    }
+
+    /// Converts a distance into a value that follows the triangle inequality, without changing the
+    /// order of distances
+    pub fn to_metric(&self, distance: Numeric) -> Numeric {
+        match self.metric {
+            DistanceMetric::SquaredEuclidean => distance.sqrt(),
+            _ => distance,
+        }
+    }
+
+    /// Distance between two samples that only differ along a single feature column. Only valid for
+    /// coordinate-wise metrics.
+    pub fn axis_distance(&self, column: usize, label_index: usize, difference: Numeric) -> Numeric {
+        let feature = if column > label_index {
+            column - 1
+        } else {
+            column
+        };
+        let weight = self
+            .weights
+            .as_ref()
+            .map_or(1.0, |weights| weights[feature]);
+        match self.metric {
+            DistanceMetric::SquaredEuclidean => weight * difference * difference,
+            DistanceMetric::Euclidean => (weight * difference * difference).sqrt(),
+            DistanceMetric::Minkowski => {
+                (weight * difference.powf(self.minkowski_p)).powf(1.0 / self.minkowski_p)
+            }
+            _ => weight * difference,
+        }
+    }
+
+    // Squared difference between two values of a column as defined by HVDM
+    fn heterogeneous_difference(&self, column: usize, x: Numeric, y: Numeric) -> f64 {
+        if !self.categorical[column] {
+            // Numeric columns with a single value can't be normalized
+            let deviation = self.deviations[column];
+            let difference = if deviation > 0.0 {
+                (x - y).abs() / (4.0 * deviation)
+            } else {
+                (x - y).abs()
+            };
+            return difference * difference;
+        }
+
+        match self.categorical_distance {
+            CategoricalDistance::ValueDifference => self
+                .value_difference
+                .as_ref()
+                .expect("Value difference metric was not fitted!")
+                .difference(column, x, y),
+            CategoricalDistance::Overlap => {
+                if (x - y).abs() > NUMERIC_DIGIT_PRECISION {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    pub fn set_weights(&mut self, weights: &str) -> Result<(), Box<dyn Error>> {
+        let mut parsed = Vec::new();
+        for weight in weights.split(',') {
+            parsed.push(weight.trim().parse::<f64>()?);
+        }
+        if parsed.iter().any(|&weight| weight < 0.0) {
+            return Err("Feature weights can't be negative!".into());
+        }
+        self.weights = Some(parsed);
+        Ok(())
+    }
+
+    pub fn get_weights(&self) -> Option<String> {
+        self.weights.as_ref().map(|weights| {
+            weights
+                .iter()
+                .map(|weight| weight.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        })
+    }
+}
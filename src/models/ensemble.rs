@@ -0,0 +1,517 @@
+// ensemble.rs
+
+//! This file implements ensembles built on top of the other model builders. The members are listed
+//! by model name in the `members` hyperparameter and every member hyperparameter is given with the
+//! member as a prefix: `knn-simple.num_neighbors` sets `num_neighbors` for every knn-simple member,
+//! `1.num_neighbors` only for the second member. Voting fits every member on the training values
+//! and combines their answers, either by majority (hard) or by averaging the class probabilities
+//! (soft), regression always averages the predictions. Bagging fits its single member on bootstrap
+//! replicates of the training values and combines them the same way. Stacking trains a meta-learner
+//! on the out-of-fold class probabilities (or predictions for regression) of the members, the folds
+//! come from the partitioning of the experiment. The meta-learner hyperparameters use the `meta`
+//! prefix.
+
+use super::{get_model_builder, training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::data::column::ColumnInfo;
+use crate::data::data_frame::DataFrame;
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+use crate::validation;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Method {
+    Voting,
+    Bagging,
+    Stacking,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Voting => "voting",
+            Self::Bagging => "bagging",
+            Self::Stacking => "stacking",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Voting {
+    Hard,
+    Soft,
+}
+
+impl Voting {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "hard" => Ok(Self::Hard),
+            "soft" => Ok(Self::Soft),
+            _ => Err(format!("Invalid voting {}, expected hard or soft", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for Voting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Hard => "hard",
+            Self::Soft => "soft",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Ensemble {
+    pub label_index: usize,
+    pub method: Method,
+    // Model names of the members, bagging replicates a single member
+    pub members: Vec<String>,
+    pub voting: Voting,
+    // Number of bootstrap replicates of a bagging ensemble
+    pub num_estimators: usize,
+    // Model name of the stacking meta-learner, picked from the task when missing
+    pub meta: Option<String>,
+    pub seed: u64,
+    // Hyperparameters of the members and of the meta-learner, keyed by prefix and name
+    member_hyperparameters: HashMap<String, String>,
+    classification: bool,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    models: Vec<Box<dyn Model>>,
+    meta_model: Option<Box<dyn Model>>,
+}
+
+impl Model for Ensemble {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        if !self.classification {
+            return self.predict(sample);
+        }
+        if let Some(meta_model) = self.meta_model.as_ref() {
+            return meta_model.label(&self.meta_sample(sample));
+        }
+
+        // Return the class with the largest vote share or probability, ties go to the smallest
+        // class
+        self.predict_proba(sample)
+            .iter()
+            .fold(None, |best: Option<(Numeric, f64)>, &(class, probability)| match best {
+                Some(best) if best.1 >= probability => Some(best),
+                _ => Some((class, probability)),
+            })
+            .map(|(class, _)| class)
+            .expect("No class to predict!")
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        if self.classification {
+            return self.label(sample);
+        }
+        match self.meta_model.as_ref() {
+            Some(meta_model) => meta_model.predict(&self.meta_sample(sample)),
+            None => {
+                self.models.iter().map(|model| model.predict(sample)).sum::<Numeric>()
+                    / self.models.len() as f64
+            }
+        }
+    }
+
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        if let Some(meta_model) = self.meta_model.as_ref() {
+            return meta_model.predict_proba(&self.meta_sample(sample));
+        }
+
+        let mut totals = vec![0.0; self.classes.len()];
+        for model in self.models.iter() {
+            match self.voting {
+                Voting::Hard => {
+                    let label = model.label(sample);
+                    if let Some(position) = self.class_position(label) {
+                        totals[position] += 1.0;
+                    }
+                }
+                Voting::Soft => {
+                    let probabilities = self.probabilities(model.as_ref(), sample);
+                    for (total, probability) in totals.iter_mut().zip(probabilities) {
+                        *total += probability;
+                    }
+                }
+            }
+        }
+        self.classes
+            .iter()
+            .zip(totals.iter())
+            .map(|(&class, total)| (class, total / self.models.len() as f64))
+            .collect()
+    }
+
+    fn type_id(&self) -> &'static str {
+        "Ensemble"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        let mut hyperparameters = self.member_hyperparameters.clone();
+        hyperparameters.insert("method".into(), self.method.to_string());
+        hyperparameters.insert("members".into(), self.members.join(","));
+        hyperparameters.insert("voting".into(), self.voting.to_string());
+        hyperparameters.insert("num_estimators".into(), self.num_estimators.to_string());
+        hyperparameters.insert(
+            "meta".into(),
+            self.meta.clone().unwrap_or_else(|| "auto".to_string()),
+        );
+        hyperparameters.insert("seed".into(), self.seed.to_string());
+        hyperparameters
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "members" => {
+                    self.members = val
+                        .split(',')
+                        .map(|member| member.trim().to_string())
+                        .filter(|member| !member.is_empty())
+                        .collect();
+                }
+                "voting" => {
+                    self.voting = Voting::from_name(val)?;
+                }
+                "num_estimators" => {
+                    self.num_estimators = val.parse::<usize>()?;
+                    if self.num_estimators == 0 {
+                        return Err("A bagging ensemble needs at least one estimator!".into());
+                    }
+                }
+                "meta" => {
+                    self.meta = match val.as_str() {
+                        "auto" => None,
+                        _ => Some(val.clone()),
+                    };
+                }
+                "seed" => {
+                    self.seed = val.parse::<u64>()?;
+                }
+                // Model names can hold dots (lvq2.1), hyperparameter names don't
+                _ if key.contains('.') => {
+                    self.member_hyperparameters.insert(key.clone(), val.clone());
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Ensemble {
+    pub fn new(label_index: usize, method: Method) -> Self {
+        Self {
+            label_index,
+            method,
+            members: Vec::new(),
+            voting: Voting::Hard,
+            num_estimators: 10,
+            meta: None,
+            seed: 0,
+            member_hyperparameters: HashMap::new(),
+            classification: true,
+            classes: Vec::new(),
+            models: Vec::new(),
+            meta_model: None,
+        }
+    }
+
+    /// Fits the members, and the meta-learner of a stacking ensemble. Must be called after the
+    /// hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.classification = match context.task.as_deref() {
+            Some("classification") | None => true,
+            Some("regression") => false,
+            Some(_) => {
+                return Err(
+                    "Invalid model task, only regression and classification are supported".into(),
+                )
+            }
+        };
+        self.classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+
+        if self.members.is_empty() {
+            return Err(
+                "The ensemble needs at least one member in the members hyperparameter!".into(),
+            );
+        }
+        if self.method == Method::Bagging && self.members.len() != 1 {
+            return Err("A bagging ensemble replicates exactly one member!".into());
+        }
+
+        self.meta_model = None;
+        self.models = match self.method {
+            Method::Voting => self.fit_members(training_values, context)?,
+            Method::Bagging => {
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                let mut models = Vec::with_capacity(self.num_estimators);
+                for replicate in 0..self.num_estimators {
+                    let bootstrap = (0..training_values.len())
+                        .map(|_| training_values[rng.gen_range(0..training_values.len())].clone())
+                        .collect::<Vec<Box<[Numeric]>>>();
+                    println!("Bagging replicate {} of {}", replicate + 1, self.num_estimators);
+                    models.push(self.fit_member(0, &bootstrap, context)?);
+                }
+                models
+            }
+            Method::Stacking => {
+                let meta_model = self.fit_meta_learner(training_values, context)?;
+                self.meta_model = Some(meta_model);
+                self.fit_members(training_values, context)?
+            }
+        };
+
+        Ok(())
+    }
+
+    // Fits every member on the training values
+    fn fit_members(
+        &self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<Vec<Box<dyn Model>>, Box<dyn Error>> {
+        (0..self.members.len())
+            .map(|position| self.fit_member(position, training_values, context))
+            .collect()
+    }
+
+    fn fit_member(
+        &self,
+        position: usize,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        let name = &self.members[position];
+        let mut builder = get_model_builder(name)?;
+        builder.with_context(context)?;
+        builder.with_hyperparameters(&self.hyperparameters_for(&[name, &position.to_string()]))?;
+        builder.build(training_values, self.label_index, None)
+    }
+
+    // Fits the members on the training part of every fold, then the meta-learner on the answers
+    // of the members for the validation parts
+    fn fit_meta_learner(
+        &self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        let partitioning = context
+            .partitioning
+            .as_ref()
+            .ok_or("No partitioning given to compute the out-of-fold predictions of the members!")?;
+        let partition = validation::get_partitioner(&partitioning.strategy)?;
+        let table = DataFrame::from_rows(training_values.to_vec())?;
+        // A class too rare to fill every fold of the training values is reported, not a panic
+        let folds = partition(&table, self.label_index, &partitioning.parameters).map_err(|e| {
+            format!("Couldn't split the training values into the stacking folds: {}", e)
+        })?;
+
+        let mut meta_features: Vec<Option<Vec<Numeric>>> = vec![None; training_values.len()];
+        for (fold, (training_indexes, validation_indexes)) in folds.iter().enumerate() {
+            println!("Stacking fold {} of {}", fold + 1, folds.len());
+            let fold_training = training_indexes
+                .iter()
+                .map(|&idx| training_values[idx].clone())
+                .collect::<Vec<_>>();
+            let fold_models = self.fit_members(&fold_training, context)?;
+            for &idx in validation_indexes.iter() {
+                meta_features[idx] = Some(self.member_answers(&fold_models, &training_values[idx]));
+            }
+        }
+
+        // Training values no fold validated on are left out of the meta-learner training
+        let meta_training = meta_features
+            .into_iter()
+            .zip(training_values.iter())
+            .filter_map(|(features, sample)| {
+                let mut row = features?;
+                row.push(sample[self.label_index]);
+                Some(row.into_boxed_slice())
+            })
+            .collect::<Vec<Box<[Numeric]>>>();
+        if meta_training.is_empty() {
+            return Err("The partitioning gave no out-of-fold predictions to stack!".into());
+        }
+        let meta_label_index = meta_training[0].len() - 1;
+        println!(
+            "Training the meta-learner on {} out-of-fold rows of {} member answers",
+            meta_training.len(),
+            meta_label_index
+        );
+
+        // The answers of the members are numeric, the label keeps the metadata of its column
+        let mut columns = vec![ColumnInfo::default(); meta_label_index];
+        columns.push(context.columns.get(self.label_index).cloned().unwrap_or_default());
+        let meta_context = TrainingContext {
+            columns,
+            partitioning: context.partitioning.clone(),
+            task: context.task.clone(),
+        };
+
+        let meta = self.meta.clone().unwrap_or_else(|| {
+            if self.classification {
+                "softmax-regression".to_string()
+            } else {
+                "linear-regression".to_string()
+            }
+        });
+        let mut builder = get_model_builder(&meta)?;
+        builder.with_context(&meta_context)?;
+        builder.with_hyperparameters(&self.hyperparameters_for(&["meta"]))?;
+        builder.build(&meta_training, meta_label_index, None)
+    }
+
+    // Returns the hyperparameters given with any of the prefixes, later prefixes take precedence
+    fn hyperparameters_for(&self, prefixes: &[&str]) -> HashMap<String, String> {
+        let mut hyperparameters = HashMap::new();
+        for prefix in prefixes.iter() {
+            for (key, val) in self.member_hyperparameters.iter() {
+                if let Some((key_prefix, name)) = key.rsplit_once('.') {
+                    if key_prefix == *prefix {
+                        hyperparameters.insert(name.to_string(), val.clone());
+                    }
+                }
+            }
+        }
+        hyperparameters
+    }
+
+    // Returns the class probabilities of every model for classification, or their predictions for
+    // regression, one after the other
+    fn member_answers(&self, models: &[Box<dyn Model>], sample: &[Numeric]) -> Vec<Numeric> {
+        models
+            .iter()
+            .flat_map(|model| {
+                if self.classification {
+                    self.probabilities(model.as_ref(), sample)
+                } else {
+                    vec![model.predict(sample)]
+                }
+            })
+            .collect()
+    }
+
+    // Returns the input of the meta-learner for a sample, the label is a placeholder
+    fn meta_sample(&self, sample: &[Numeric]) -> Vec<Numeric> {
+        let mut meta_sample = self.member_answers(&self.models, sample);
+        meta_sample.push(0.0);
+        meta_sample
+    }
+
+    // Returns the probability a model gives to every class of the ensemble, in class order
+    fn probabilities(&self, model: &dyn Model, sample: &[Numeric]) -> Vec<f64> {
+        let mut probabilities = vec![0.0; self.classes.len()];
+        for (class, probability) in model.predict_proba(sample) {
+            if let Some(position) = self.class_position(class) {
+                probabilities[position] = probability;
+            }
+        }
+        probabilities
+    }
+
+    fn class_position(&self, label: Numeric) -> Option<usize> {
+        self.classes
+            .iter()
+            .position(|class| (class - label).abs() < NUMERIC_DIGIT_PRECISION)
+    }
+}
+
+pub struct EnsembleTrainer {
+    method: Method,
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl EnsembleTrainer {
+    pub fn bagging() -> Self {
+        Self {
+            method: Method::Bagging,
+            ..Self::new()
+        }
+    }
+
+    pub fn stacking() -> Self {
+        Self {
+            method: Method::Stacking,
+            ..Self::new()
+        }
+    }
+}
+
+impl ModelBuilder for EnsembleTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            method: Method::Voting,
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = Ensemble::new(target_value_idx, self.method);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built {} ensemble of {} models: {}",
+            model.method,
+            model.models.len(),
+            model.members.join(", ")
+        );
+
+        Ok(Box::new(model))
+    }
+}
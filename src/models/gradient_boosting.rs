@@ -0,0 +1,434 @@
+// gradient_boosting.rs
+
+//! This file implements gradient boosted trees. Starting from a constant score, every stage fits a
+//! shallow regression tree to the negative gradient of the loss (the pseudo-residuals) and adds it
+//! to the score scaled by the learning rate. Regression uses the squared loss. Classification uses
+//! the log loss of a logistic score, with a single score for binary problems and one score per
+//! class (one-vs-rest) for multiclass problems. Each stage can be fitted on a random subsample of
+//! the training values, and boosting stops early when the loss on a validation split taken from
+//! the training values stops improving.
+
+use super::regression_tree::RegressionTree;
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+
+const MIN_PROBABILITY: f64 = 1e-15;
+
+#[derive(Serialize, Deserialize)]
+pub struct GradientBoosting {
+    pub label_index: usize,
+    pub learning_rate: f64,
+    pub num_stages: usize,
+    pub subsample: f64,
+    pub max_depth: usize,
+    pub validation_fraction: f64,
+    // Number of stages without improvement on the validation split before stopping, no early
+    // stopping when missing
+    pub patience: Option<usize>,
+    classification: bool,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    // Initial score of each output, there is one output for regression and binary classification
+    // and one per class otherwise
+    initial_scores: Vec<f64>,
+    // Trees of each stage, one per output
+    stages: Vec<Vec<RegressionTree>>,
+}
+
+impl Model for GradientBoosting {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        if !self.classification {
+            return self.scores(sample)[0];
+        }
+        // Return the class with the largest probability, ties go to the smallest class
+        self.probabilities(&self.scores(sample))
+            .into_iter()
+            .fold(None, |best: Option<(Numeric, f64)>, (class, probability)| match best {
+                Some(best) if best.1 >= probability => Some(best),
+                _ => Some((class, probability)),
+            })
+            .map(|(class, _)| class)
+            .expect("No classes found!")
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        if self.classification {
+            return self.label(sample);
+        }
+        self.scores(sample)[0]
+    }
+
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        if !self.classification {
+            return vec![(self.predict(sample), 1.0)];
+        }
+        self.probabilities(&self.scores(sample))
+    }
+
+    fn type_id(&self) -> &'static str {
+        "GradientBoosting"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("learning_rate".into(), self.learning_rate.to_string()),
+            ("num_stages".into(), self.num_stages.to_string()),
+            ("subsample".into(), self.subsample.to_string()),
+            ("max_depth".into(), self.max_depth.to_string()),
+            ("validation_fraction".into(), self.validation_fraction.to_string()),
+            (
+                "patience".into(),
+                self.patience
+                    .map_or("none".to_string(), |patience| patience.to_string()),
+            ),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "learning_rate" => {
+                    self.learning_rate = val.parse::<f64>()?;
+                }
+                "num_stages" => {
+                    self.num_stages = val.parse::<usize>()?;
+                }
+                "subsample" => {
+                    let subsample = val.parse::<f64>()?;
+                    if subsample <= 0.0 || subsample > 1.0 {
+                        return Err("The subsample fraction must be in (0, 1]!".into());
+                    }
+                    self.subsample = subsample;
+                }
+                "max_depth" => {
+                    self.max_depth = val.parse::<usize>()?;
+                }
+                "validation_fraction" => {
+                    let validation_fraction = val.parse::<f64>()?;
+                    if !(0.0..1.0).contains(&validation_fraction) {
+                        return Err("The validation fraction must be in [0, 1)!".into());
+                    }
+                    self.validation_fraction = validation_fraction;
+                }
+                "patience" => {
+                    self.patience = match val.as_str() {
+                        "none" => None,
+                        _ => Some(val.parse::<usize>()?),
+                    };
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl GradientBoosting {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            learning_rate: 0.1,
+            num_stages: 100,
+            subsample: 1.0,
+            max_depth: 3,
+            validation_fraction: 0.1,
+            patience: Some(10),
+            classification: false,
+            classes: Vec::new(),
+            initial_scores: Vec::new(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Fits the boosting stages on the training values. Must be called after the hyperparameters
+    /// are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.classification = match context.task.as_deref() {
+            Some("classification") | None => true,
+            Some("regression") => false,
+            Some(_) => {
+                return Err(
+                    "Invalid model task, only regression and classification are supported".into(),
+                )
+            }
+        };
+        if self.classification {
+            self.classes = context
+                .classes(self.label_index)
+                .unwrap_or_else(|| training_classes(training_values, self.label_index));
+            if self.classes.len() < 2 {
+                return Err("Gradient boosting needs at least two classes!".into());
+            }
+        }
+
+        // Take the validation split for early stopping out of the training values
+        let mut rng = rand::thread_rng();
+        let mut indexes = (0..training_values.len()).collect::<Vec<usize>>();
+        let mut validation_indexes = Vec::new();
+        if self.patience.is_some() && self.validation_fraction > 0.0 {
+            if training_values.len() < 2 {
+                return Err("Not enough training values to hold out a validation split!".into());
+            }
+            indexes.shuffle(&mut rng);
+            let num_validation = ((training_values.len() as f64 * self.validation_fraction)
+                .round() as usize)
+                .clamp(1, training_values.len() - 1);
+            validation_indexes = indexes.split_off(training_values.len() - num_validation);
+        }
+
+        // Start from the mean of each target, as a log-odds for classification
+        let targets = training_values
+            .iter()
+            .map(|sample| self.targets(sample))
+            .collect::<Vec<Vec<f64>>>();
+        let num_outputs = targets[0].len();
+        self.initial_scores = (0..num_outputs)
+            .map(|output| {
+                let mean = indexes
+                    .iter()
+                    .map(|&idx| targets[idx][output])
+                    .sum::<f64>()
+                    / indexes.len() as f64;
+                if self.classification {
+                    let mean = mean.clamp(MIN_PROBABILITY, 1.0 - MIN_PROBABILITY);
+                    (mean / (1.0 - mean)).ln()
+                } else {
+                    mean
+                }
+            })
+            .collect();
+
+        // Keep the scores of every training value up to date as stages are added
+        let mut scores = vec![self.initial_scores.clone(); training_values.len()];
+        self.stages.clear();
+
+        let subsample_size = ((indexes.len() as f64 * self.subsample).round() as usize).max(1);
+        let mut best_loss = Numeric::INFINITY;
+        let mut best_num_stages = 0;
+        for stage in 0..self.num_stages {
+            // Fit each output's tree on the pseudo-residuals of a subsample of the training values
+            let mut subsample = indexes.clone();
+            subsample.shuffle(&mut rng);
+            subsample.truncate(subsample_size);
+
+            let mut trees = Vec::with_capacity(num_outputs);
+            for output in 0..num_outputs {
+                let residuals = subsample
+                    .iter()
+                    .map(|&idx| {
+                        let mut row = training_values[idx].clone();
+                        row[self.label_index] =
+                            targets[idx][output] - self.output_value(scores[idx][output]);
+                        row
+                    })
+                    .collect::<Vec<Box<[Numeric]>>>();
+
+                let mut tree = RegressionTree::new(self.label_index);
+                tree.max_depth = Some(self.max_depth);
+                tree.fit(&residuals, context)?;
+                trees.push(tree);
+            }
+
+            for (sample, score) in training_values.iter().zip(scores.iter_mut()) {
+                for (tree, output_score) in trees.iter().zip(score.iter_mut()) {
+                    *output_score += self.learning_rate * tree.predict(sample);
+                }
+            }
+            self.stages.push(trees);
+
+            let training_loss = self.loss(training_values, &scores, &indexes);
+            if validation_indexes.is_empty() {
+                println!("Stage {}: training loss: {}", stage, training_loss);
+                continue;
+            }
+
+            // Stop when the validation loss hasn't improved for the given number of stages
+            let validation_loss = self.loss(training_values, &scores, &validation_indexes);
+            println!(
+                "Stage {}: training loss: {}, validation loss: {}",
+                stage, training_loss, validation_loss
+            );
+            if validation_loss < best_loss - NUMERIC_DIGIT_PRECISION {
+                best_loss = validation_loss;
+                best_num_stages = self.stages.len();
+            } else if self
+                .patience
+                .is_some_and(|patience| self.stages.len() - best_num_stages >= patience)
+            {
+                println!(
+                    "Stopping early, keeping the first {} stages (validation loss: {})",
+                    best_num_stages, best_loss
+                );
+                self.stages.truncate(best_num_stages);
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn num_fitted_stages(&self) -> usize {
+        self.stages.len()
+    }
+
+    // Returns the values each output is fitted to: the label for regression and whether the sample
+    // is of the (positive) class for classification
+    fn targets(&self, sample: &[Numeric]) -> Vec<f64> {
+        let label = sample[self.label_index];
+        let is_class = |class: &Numeric| {
+            if (label - class).abs() < NUMERIC_DIGIT_PRECISION {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        if !self.classification {
+            vec![label]
+        } else if self.classes.len() == 2 {
+            vec![is_class(&self.classes[1])]
+        } else {
+            self.classes.iter().map(is_class).collect()
+        }
+    }
+
+    // Maps a score to the value compared with the target: a probability for classification
+    fn output_value(&self, score: f64) -> f64 {
+        if self.classification {
+            1.0 / (1.0 + (-score).exp())
+        } else {
+            score
+        }
+    }
+
+    fn scores(&self, sample: &[Numeric]) -> Vec<f64> {
+        let mut scores = self.initial_scores.clone();
+        for trees in self.stages.iter() {
+            for (tree, score) in trees.iter().zip(scores.iter_mut()) {
+                *score += self.learning_rate * tree.predict(sample);
+            }
+        }
+        scores
+    }
+
+    fn probabilities(&self, scores: &[f64]) -> ClassProbabilities {
+        if self.classes.len() == 2 {
+            let probability = self.output_value(scores[0]);
+            return vec![
+                (self.classes[0], 1.0 - probability),
+                (self.classes[1], probability),
+            ];
+        }
+
+        // One-vs-rest probabilities don't add up to one, so they are normalized
+        let probabilities = scores
+            .iter()
+            .map(|&score| self.output_value(score))
+            .collect::<Vec<f64>>();
+        let total = probabilities.iter().sum::<f64>();
+        self.classes
+            .iter()
+            .zip(probabilities.iter())
+            .map(|(&class, probability)| {
+                let share = if total > 0.0 {
+                    probability / total
+                } else {
+                    1.0 / self.classes.len() as f64
+                };
+                (class, share)
+            })
+            .collect()
+    }
+
+    // Mean squared error for regression and mean log loss for classification over the given
+    // training values
+    fn loss(&self, training_values: &[Box<[Numeric]>], scores: &[Vec<f64>], indexes: &[usize]) -> f64 {
+        indexes
+            .iter()
+            .map(|&idx| {
+                let target = training_values[idx][self.label_index];
+                if !self.classification {
+                    return (scores[idx][0] - target).powi(2);
+                }
+                let probability = self
+                    .probabilities(&scores[idx])
+                    .iter()
+                    .find(|(class, _)| (class - target).abs() < NUMERIC_DIGIT_PRECISION)
+                    .map_or(0.0, |(_, probability)| *probability);
+                -probability.clamp(MIN_PROBABILITY, 1.0).ln()
+            })
+            .sum::<f64>()
+            / indexes.len() as f64
+    }
+}
+
+pub struct GradientBoostingTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for GradientBoostingTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = GradientBoosting::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built gradient boosting model with {} stages",
+            model.num_fitted_stages()
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -0,0 +1,108 @@
+// kmeans.rs
+
+//! This file implements the building blocks of k-means clustering: k-means++ seeding, which picks
+//! every new center with probability proportional to its squared distance to the closest center
+//! already picked, and Lloyd's iterations, which alternate between assigning every row to its
+//! closest center and moving every center to the mean of its rows until no assignment changes.
+//! Distances are squared Euclidean over the given rows.
+
+use crate::types::Numeric;
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+pub fn squared_distance(x: &[Numeric], z: &[Numeric]) -> f64 {
+    x.iter().zip(z.iter()).map(|(a, b)| (a - b).powi(2)).sum()
+}
+
+/// Returns the position and squared distance of the center closest to the row
+pub fn nearest_center(centers: &[Vec<Numeric>], row: &[Numeric]) -> (usize, f64) {
+    centers
+        .iter()
+        .enumerate()
+        .map(|(position, center)| (position, squared_distance(center, row)))
+        .fold((0, f64::INFINITY), |best, candidate| {
+            if candidate.1 < best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// Picks k initial centers among the rows with k-means++ seeding
+pub fn plus_plus(rows: &[Vec<Numeric>], k: usize, rng: &mut StdRng) -> Vec<Vec<Numeric>> {
+    let mut centers = vec![rows[rng.gen_range(0..rows.len())].clone()];
+    let mut distances = rows
+        .iter()
+        .map(|row| squared_distance(row, &centers[0]))
+        .collect::<Vec<f64>>();
+    while centers.len() < k.min(rows.len()) {
+        let total = distances.iter().sum::<f64>();
+        let chosen = if total > 0.0 {
+            let mut target = rng.gen::<f64>() * total;
+            distances
+                .iter()
+                .position(|distance| {
+                    target -= distance;
+                    target < 0.0
+                })
+                .unwrap_or(rows.len() - 1)
+        } else {
+            // Every row sits on a center already, any row will do
+            rng.gen_range(0..rows.len())
+        };
+        centers.push(rows[chosen].clone());
+        for (distance, row) in distances.iter_mut().zip(rows.iter()) {
+            *distance = distance.min(squared_distance(row, &rows[chosen]));
+        }
+    }
+    centers
+}
+
+/// Runs Lloyd's iterations from the given centers. Returns the final centers, the center
+/// assigned to every row and the inertia (sum of squared distances of the rows to their center).
+/// Centers that lose all their rows stay where they are. The rows are always assigned once, even
+/// when `max_iterations` is 0.
+pub fn lloyd(
+    rows: &[Vec<Numeric>],
+    mut centers: Vec<Vec<Numeric>>,
+    max_iterations: usize,
+) -> (Vec<Vec<Numeric>>, Vec<usize>, f64) {
+    let mut assignments = vec![usize::MAX; rows.len()];
+    for _ in 0..max_iterations.max(1) {
+        let mut changed = false;
+        for (assignment, row) in assignments.iter_mut().zip(rows.iter()) {
+            let (nearest, _) = nearest_center(&centers, row);
+            if *assignment != nearest {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let num_columns = rows[0].len();
+        let mut sums = vec![vec![0.0; num_columns]; centers.len()];
+        let mut counts = vec![0; centers.len()];
+        for (&assignment, row) in assignments.iter().zip(rows.iter()) {
+            counts[assignment] += 1;
+            for (sum, value) in sums[assignment].iter_mut().zip(row.iter()) {
+                *sum += value;
+            }
+        }
+        for ((center, sum), &count) in centers.iter_mut().zip(sums).zip(counts.iter()) {
+            if count > 0 {
+                *center = sum.iter().map(|value| value / count as f64).collect();
+            }
+        }
+    }
+
+    let inertia = assignments
+        .iter()
+        .zip(rows.iter())
+        .map(|(&assignment, row)| squared_distance(&centers[assignment], row))
+        .sum();
+    (centers, assignments, inertia)
+}
@@ -1,154 +1,117 @@
 // knn.rs
 
-//! This file implements the logic to predict values using a k-nearest neighbor learner
+//! This file implements the logic to predict values using a k-nearest neighbor learner. The closest
+//! examples are found through a spatial index built when the model is fitted. Labels are chosen by
+//! a vote of the neighbors, where each neighbor counts once (majority) or is weighted by the inverse
+//! of its distance or by the kernel used for regression. Ties go to the class of the nearest
+//! neighbor. When the examples have sample weights, every vote and every term of the kernel
+//! smoothing is also scaled by the weight of its example.
 
-use super::Model;
+use super::distance::{CategoricalDistance, Distance, DistanceMetric};
+use super::spatial_index::{IndexKind, SpatialIndex};
+use super::{training_classes, Model, TrainingContext};
 
-use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
 use std::error::Error;
+use std::fmt;
 
 use plotly::color::Rgb;
 use plotly::common::{Fill, Marker, Mode, Orientation, Position, Title};
 use plotly::layout::{Axis, Legend};
 use plotly::{Layout, Plot, Scatter};
 
-#[derive(Debug)]
+const DEFAULT_LEAF_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Voting {
+    Majority,
+    InverseDistance,
+    Kernel,
+}
+
+impl Voting {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "majority" => Ok(Self::Majority),
+            "inverse-distance" => Ok(Self::InverseDistance),
+            "kernel" => Ok(Self::Kernel),
+            _ => Err(format!("Unsupported voting strategy: {}", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for Voting {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Majority => "majority",
+            Self::InverseDistance => "inverse-distance",
+            Self::Kernel => "kernel",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct KNearestNeighbor {
-    pub label_examples: Vec<Box<[Numeric]>>,
+    label_examples: Vec<Box<[Numeric]>>,
     pub label_index: usize,
     pub num_neighbors: usize,
     pub epsilon: f64,
     pub gamma: f64,
+    pub distance: Distance,
+    pub index_kind: IndexKind,
+    pub leaf_size: usize,
+    pub voting: Voting,
+    // Weight of each label example, every example counts the same when missing
+    example_weights: Option<Vec<f64>>,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    index: SpatialIndex,
 }
 
 impl Model for KNearestNeighbor {
     fn label(&self, sample: &[Numeric]) -> Numeric {
-        // Calculate distances between each example and the k nearest neighbors
-        let mut distances: Vec<(usize, Numeric)> = self
-            .label_examples
-            .iter()
-            .map(|example| {
-                // Prepare iterator over point we want to classify
-                let sample_iter = sample
-                    .iter()
-                    .enumerate()
-                    .filter(|&(idx, _)| idx != self.label_index)
-                    .map(|(_, v)| v);
-
-                // Prepare iterator over training example 
-                let example_iter = example
-                    .iter()
-                    .enumerate()
-                    .filter(|&(idx, _)| idx != self.label_index)
-                    .map(|(_, v)| v);
-
-                // Calculate distance using L2-norm
-                sample_iter
-                    .zip(example_iter)
-                    // Euclidean distance
-                    .fold(0.0, |acc, (e1, e2)| acc + (e2 - e1) * (e2 - e1))
-            })
-            .enumerate()
-            .collect();
-        // Sort the distances by distance
-        distances.sort_by(|(_, x), (_, y)| x.abs().partial_cmp(&y.abs()).unwrap());
-
-        // Accumulate neighbors
-        let neighbors = distances.iter().take(self.num_neighbors).collect::<Vec<_>>();
-
-        // Get the label count of the k nearest neighbors
-        let mut label_vote = HashMap::new();
-        neighbors.iter()
-            .for_each(|&(neighbor_idx, _)| {
-                let key = (self.label_examples[*neighbor_idx][self.label_index]
-                    / NUMERIC_DIGIT_PRECISION) as i64;
-                let counter = label_vote.entry(key).or_insert(0);
-                *counter += 1;
-            });
-
-        // Get the most common label
-        let mode = label_vote
-            .iter()
-            .max_by_key(|&(_, count)| count)
-            .map(|(val, _)| val)
-            .expect("No mode found!");
-
-        println!("Neighbors: {:?} -> label: {}", neighbors, ((*mode as f64) * NUMERIC_DIGIT_PRECISION));
-
-        // return the most common label
-        (*mode as f64) * NUMERIC_DIGIT_PRECISION
+        self.vote(&self.neighbors(sample, &|_| false))
     }
 
     fn predict(&self, sample: &[Numeric]) -> Numeric {
-        // Calculate distances between each example and the k nearest neighbors
-        let mut distances: Vec<(usize, Numeric)> = self
-            .label_examples
-            .iter()
-            .map(|example| {
-                // Prepare iterator over point we want to classify
-                let sample_iter = sample
-                    .iter()
-                    .enumerate()
-                    .filter(|&(idx, _)| idx != self.label_index)
-                    .map(|(_, v)| v);
-
-                // Prepare iterator over training example 
-                let example_iter = example
-                    .iter()
-                    .enumerate()
-                    .filter(|&(idx, _)| idx != self.label_index)
-                    .map(|(_, v)| v);
-
-                // Calculate distance using L2-norm
-                sample_iter
-                    .zip(example_iter)
-                    // Euclidean distance
-                    .fold(0.0, |acc, (e1, e2)| acc + (e2 - e1) * (e2 - e1))
-            })
-            .enumerate()
-            .collect();
-
-        // Sort the distances by distance
-        distances.sort_by(|(_, x), (_, y)| x.abs().partial_cmp(&y.abs()).unwrap());
-
-        // Accumulate neighbors
-        let neighbors = distances.iter().take(self.num_neighbors).collect::<Vec<_>>();
-
-        // Calculate kernel metrics
-        let kernel_metric = neighbors.iter()
-            .map(|&(_, dist)| (-self.gamma * dist).exp())
-            .collect::<Vec<f64>>();
-
-        // Calculate numerator of kernel smoothing
-        let numerator = neighbors.iter()
-            .map(|&(idx, _)| idx)
-            .zip(kernel_metric.iter())
-            .fold(0.0, |acc, (idx, metric)| {
-                acc + metric * self.label_examples[*idx][self.label_index]
-            });
-
-        // Calculate denominator of kernel smoothing
-        let denominator = kernel_metric.iter().sum::<f64>();
-
-        println!("Neighbors: {:?} -> point: {}", neighbors, (numerator / denominator));
-        println!("Kernel metrics: {:?}", kernel_metric);
+        self.smooth(&self.neighbors(sample, &|_| false))
+    }
 
-        // return the most common label
-        numerator / denominator
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        self.vote_shares(&self.neighbors(sample, &|_| false))
     }
 
     fn type_id(&self) -> &'static str {
         "KNearestNeighbor"
     }
 
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
     fn get_hyperparameters(&self) -> HashMap<String, String> {
-        let ret = HashMap::from([
+        let mut ret = HashMap::from([
             ("num_neighbors".into(), self.num_neighbors.to_string()),
             ("epsilon".into(), self.epsilon.to_string()),
             ("gamma".into(), self.gamma.to_string()),
+            ("distance".into(), self.distance.metric.to_string()),
+            ("minkowski_p".into(), self.distance.minkowski_p.to_string()),
+            (
+                "categorical_distance".into(),
+                self.distance.categorical_distance.to_string(),
+            ),
+            ("index".into(), self.index_kind.to_string()),
+            ("leaf_size".into(), self.leaf_size.to_string()),
+            ("voting".into(), self.voting.to_string()),
         ]);
+        if let Some(weights) = self.distance.get_weights() {
+            ret.insert("feature_weights".into(), weights);
+        }
         ret
     }
 
@@ -164,6 +127,27 @@ impl Model for KNearestNeighbor {
                 "gamma" => {
                     self.gamma = val.parse::<f64>()?;
                 }
+                "distance" => {
+                    self.distance.metric = DistanceMetric::from_name(val)?;
+                }
+                "minkowski_p" => {
+                    self.distance.minkowski_p = val.parse::<f64>()?;
+                }
+                "categorical_distance" => {
+                    self.distance.categorical_distance = CategoricalDistance::from_name(val)?;
+                }
+                "feature_weights" => {
+                    self.distance.set_weights(val)?;
+                }
+                "index" => {
+                    self.index_kind = IndexKind::from_name(val)?;
+                }
+                "leaf_size" => {
+                    self.leaf_size = val.parse::<usize>()?;
+                }
+                "voting" => {
+                    self.voting = Voting::from_name(val)?;
+                }
                 _ => {}
             }
         }
@@ -172,6 +156,236 @@ impl Model for KNearestNeighbor {
 }
 
 impl KNearestNeighbor {
+    pub fn new(
+        label_examples: Vec<Box<[Numeric]>>,
+        label_index: usize,
+        num_neighbors: usize,
+        epsilon: f64,
+        gamma: f64,
+    ) -> Self {
+        Self {
+            label_examples,
+            label_index,
+            num_neighbors,
+            epsilon,
+            gamma,
+            distance: Distance::default(),
+            index_kind: IndexKind::Auto,
+            leaf_size: DEFAULT_LEAF_SIZE,
+            voting: Voting::Majority,
+            example_weights: None,
+            classes: Vec::new(),
+            index: SpatialIndex::default(),
+        }
+    }
+
+    /// Calculates the statistics the distance metric needs from the training data and builds the
+    /// index over the label examples. Must be called after the hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.distance.fit(
+            training_values,
+            self.label_index,
+            &context.categorical_columns(),
+        )?;
+
+        // Record the classes so probabilities can be given for classes no neighbor votes for
+        self.classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+
+        self.build_index()
+    }
+
+    /// Gives a weight to every label example, the weights scale the votes of the examples
+    pub fn set_example_weights(&mut self, weights: &[Numeric]) -> Result<(), Box<dyn Error>> {
+        if weights.len() != self.label_examples.len() {
+            return Err("Expected one sample weight per label example!".into());
+        }
+        if weights.iter().any(|&weight| weight < 0.0 || !weight.is_finite()) {
+            return Err("Sample weights must be finite and not negative!".into());
+        }
+        self.example_weights = Some(weights.to_vec());
+        Ok(())
+    }
+
+    /// Keeps only the label examples marked as active and rebuilds the index
+    pub fn retain_examples(&mut self, active: &[bool]) -> Result<(), Box<dyn Error>> {
+        if let Some(weights) = self.example_weights.as_mut() {
+            let mut active = active.iter();
+            weights.retain(|_| *active.next().unwrap_or(&false));
+        }
+        let mut active = active.iter();
+        self.label_examples
+            .retain(|_| *active.next().unwrap_or(&false));
+        self.build_index()
+    }
+
+    /// Predicts the value of a sample using only the label examples marked as active
+    pub fn predict_among(&self, sample: &[Numeric], active: &[bool]) -> Numeric {
+        self.smooth(&self.neighbors(sample, &|idx| !active[idx]))
+    }
+
+    fn build_index(&mut self) -> Result<(), Box<dyn Error>> {
+        self.index = SpatialIndex::build(
+            self.index_kind,
+            &self.label_examples,
+            &self.distance,
+            self.label_index,
+            self.leaf_size,
+        )?;
+        Ok(())
+    }
+
+    // Returns the index and distance of the k examples closest to the sample, sorted by distance
+    fn neighbors(&self, sample: &[Numeric], skip: &dyn Fn(usize) -> bool) -> Vec<(usize, Numeric)> {
+        self.index.nearest(
+            &self.label_examples,
+            &self.distance,
+            self.label_index,
+            sample,
+            self.num_neighbors,
+            skip,
+        )
+    }
+
+    /// Returns the index and distance of every example within the radius of the sample, sorted by
+    /// distance
+    pub fn within(&self, sample: &[Numeric], radius: Numeric) -> Vec<(usize, Numeric)> {
+        self.index.within(
+            &self.label_examples,
+            &self.distance,
+            self.label_index,
+            sample,
+            radius,
+            &|_| false,
+        )
+    }
+
+    // Returns the weight of each neighbor's vote
+    fn vote_weights(&self, neighbors: &[(usize, Numeric)]) -> Vec<f64> {
+        let weights = match self.voting {
+            Voting::Majority => vec![1.0; neighbors.len()],
+            Voting::InverseDistance => {
+                // Neighbors at the same point as the sample outweigh every other neighbor
+                if neighbors.iter().any(|&(_, dist)| dist <= 0.0) {
+                    neighbors
+                        .iter()
+                        .map(|&(_, dist)| if dist <= 0.0 { 1.0 } else { 0.0 })
+                        .collect()
+                } else {
+                    neighbors.iter().map(|&(_, dist)| 1.0 / dist).collect()
+                }
+            }
+            Voting::Kernel => neighbors
+                .iter()
+                .map(|&(_, dist)| (-self.gamma * dist).exp())
+                .collect(),
+        };
+        self.weigh_examples(neighbors, weights)
+    }
+
+    // Scales the value of each neighbor by the weight of its example, if the examples are weighted
+    fn weigh_examples(&self, neighbors: &[(usize, Numeric)], values: Vec<f64>) -> Vec<f64> {
+        match self.example_weights.as_ref() {
+            Some(weights) => neighbors
+                .iter()
+                .zip(values)
+                .map(|(&(idx, _), value)| weights[idx] * value)
+                .collect(),
+            None => values,
+        }
+    }
+
+    // Returns the total vote weight of each label, in the order the nearest neighbor of each label
+    // appears
+    fn class_votes(&self, neighbors: &[(usize, Numeric)]) -> Vec<(i64, f64)> {
+        let mut label_vote: Vec<(i64, f64)> = Vec::new();
+        for (&(neighbor_idx, _), weight) in neighbors.iter().zip(self.vote_weights(neighbors)) {
+            let key = (self.label_examples[neighbor_idx][self.label_index]
+                / NUMERIC_DIGIT_PRECISION) as i64;
+            match label_vote.iter_mut().find(|(label, _)| *label == key) {
+                Some((_, total)) => *total += weight,
+                None => label_vote.push((key, weight)),
+            }
+        }
+        label_vote
+    }
+
+    /// Returns the share of the vote each class gets from the neighbors
+    pub fn vote_shares(&self, neighbors: &[(usize, Numeric)]) -> ClassProbabilities {
+        let votes = self.class_votes(neighbors);
+        let total = votes.iter().map(|(_, weight)| weight).sum::<f64>();
+
+        self.classes
+            .iter()
+            .map(|&class| {
+                let key = (class / NUMERIC_DIGIT_PRECISION) as i64;
+                let share = if total > 0.0 {
+                    votes
+                        .iter()
+                        .find(|(voted, _)| *voted == key)
+                        .map_or(0.0, |(_, weight)| weight / total)
+                } else {
+                    // Nobody voted, every class is equally likely
+                    1.0 / self.classes.len() as f64
+                };
+                (class, share)
+            })
+            .collect()
+    }
+
+    /// Returns the label with the largest vote among the neighbors
+    pub fn vote(&self, neighbors: &[(usize, Numeric)]) -> Numeric {
+        // Get the label votes of the k nearest neighbors
+        let label_vote = self.class_votes(neighbors);
+
+        // Get the label with the most votes, on a tie the label of the nearer neighbor wins since
+        // it comes first
+        let mode = label_vote
+            .iter()
+            .fold(None, |best: Option<&(i64, f64)>, vote| match best {
+                Some(best) if best.1 >= vote.1 => Some(best),
+                _ => Some(vote),
+            })
+            .map(|(val, _)| val)
+            .expect("No mode found!");
+
+        println!("Neighbors: {:?} -> label: {}", neighbors, ((*mode as f64) * NUMERIC_DIGIT_PRECISION));
+
+        // return the label with the most votes
+        (*mode as f64) * NUMERIC_DIGIT_PRECISION
+    }
+
+    /// Returns the kernel smoothed value of the neighbors
+    pub fn smooth(&self, neighbors: &[(usize, Numeric)]) -> Numeric {
+        // Calculate kernel metrics
+        let kernel_metric = neighbors.iter()
+            .map(|&(_, dist)| (-self.gamma * dist).exp())
+            .collect::<Vec<f64>>();
+        let kernel_metric = self.weigh_examples(neighbors, kernel_metric);
+
+        // Calculate numerator of kernel smoothing
+        let numerator = neighbors.iter()
+            .map(|&(idx, _)| idx)
+            .zip(kernel_metric.iter())
+            .fold(0.0, |acc, (idx, metric)| {
+                acc + metric * self.label_examples[idx][self.label_index]
+            });
+
+        // Calculate denominator of kernel smoothing
+        let denominator = kernel_metric.iter().sum::<f64>();
+
+        println!("Neighbors: {:?} -> point: {}", neighbors, (numerator / denominator));
+        println!("Kernel metrics: {:?}", kernel_metric);
+
+        // return the most common label
+        numerator / denominator
+    }
+
     // This function takes a vector of training data and generate the voronoi diagram
     // using plotters
     pub fn generate_voronoi_diagram(&self) -> Result<(), Box<dyn std::error::Error>> {
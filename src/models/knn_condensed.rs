@@ -4,6 +4,7 @@
 
 use super::Model;
 use super::ModelBuilder;
+use super::TrainingContext;
 
 use crate::models::knn::KNearestNeighbor;
 use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
@@ -13,6 +14,7 @@ use std::error::Error;
 
 pub struct CondensedKNearestNeighborTrainer {
     hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
     num_neighbors: usize,
     epsilon: f64,
     show_voronoi: bool,
@@ -25,6 +27,7 @@ impl ModelBuilder for CondensedKNearestNeighborTrainer {
     {
         Self {
             hyperparameters: None,
+            context: TrainingContext::default(),
             num_neighbors: 1,
             epsilon: NUMERIC_DIGIT_PRECISION,
             show_voronoi: false,
@@ -39,10 +42,16 @@ impl ModelBuilder for CondensedKNearestNeighborTrainer {
         Ok(())
     }
 
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
     fn build(
         &mut self,
         training_values: &[Box<[Numeric]>],
         target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
     ) -> Result<Box<dyn Model>, Box<dyn Error>> {
         if training_values.is_empty() {
             return Err("Empty training set given!".into());
@@ -51,14 +60,10 @@ impl ModelBuilder for CondensedKNearestNeighborTrainer {
             return Err("Target value index is out of bounds!".into());
         }
 
-        // Build iterator over training values
-        let mut training_data = training_values.iter();
-
-        // Build examples for the algorithm
+        // Build examples for the algorithm, the index is built over all training values and
+        // only the examples marked as active take part in the predictions
         let mut label_examples = Vec::new();
-
-        // Push first sample of training data
-        label_examples.push(training_data.next().unwrap().clone());
+        label_examples.extend(training_values.iter().cloned());
 
         // Calculate training value mean
         let training_value_mean = training_values.iter().fold(0.0, |acc, x| {
@@ -71,28 +76,21 @@ impl ModelBuilder for CondensedKNearestNeighborTrainer {
         }) / (training_values.len() - 1) as f64;
 
         // Build k-nearest neighbors model with the label examples
-        let mut model = KNearestNeighbor {
-            num_neighbors: self.num_neighbors,
-            label_index: target_value_idx,
+        let mut model = KNearestNeighbor::new(
             label_examples,
-            epsilon: self.epsilon,
-            gamma: training_value_variance.sqrt(),
-        };
+            target_value_idx,
+            self.num_neighbors,
+            self.epsilon,
+            training_value_variance.sqrt(),
+        );
 
         if let Some(hyperparameters) = self.hyperparameters.as_ref() {
             model.set_hyperparameters(hyperparameters)?;
         }
+        model.fit(training_values, &self.context)?;
 
-        // Iterat over training data
-        for (idx, sample) in training_data.enumerate() {
-            let prediction = model.predict(sample);
-
-            if (prediction - sample[model.label_index]).abs() > self.epsilon {
-                // Value doesn't match, add to the label example set
-                model.label_examples.push(sample.clone());
-                println!("Sample {} was added", idx);
-            }
-        }
+        let active = condense(&model, training_values, self.epsilon);
+        model.retain_examples(&active)?;
 
         if self.show_voronoi {
             model.generate_voronoi_diagram()?;
@@ -101,3 +99,24 @@ impl ModelBuilder for CondensedKNearestNeighborTrainer {
         Ok(Box::new(model))
     }
 }
+
+/// Returns which training values are kept as label examples: starting with the first one, every
+/// training value the kept examples mispredict is added. The model must hold all the training values
+/// as label examples.
+pub fn condense(model: &KNearestNeighbor, training_values: &[Box<[Numeric]>], epsilon: f64) -> Vec<bool> {
+    // Start with the first sample of training data
+    let mut active = vec![false; training_values.len()];
+    active[0] = true;
+
+    // Iterat over training data
+    for (idx, sample) in training_values.iter().enumerate().skip(1) {
+        let prediction = model.predict_among(sample, &active);
+
+        if (prediction - sample[model.label_index]).abs() > epsilon {
+            // Value doesn't match, add to the label example set
+            active[idx] = true;
+            println!("Sample {} was added", idx);
+        }
+    }
+    active
+}
@@ -4,6 +4,7 @@
 
 use super::Model;
 use super::ModelBuilder;
+use super::TrainingContext;
 
 use crate::models::knn::KNearestNeighbor;
 use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
@@ -13,6 +14,7 @@ use std::error::Error;
 
 pub struct EditedKNearestNeighborTrainer {
     hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
     num_neighbors: usize,
     epsilon: f64,
     show_voronoi: bool,
@@ -27,6 +29,7 @@ impl ModelBuilder for EditedKNearestNeighborTrainer {
             num_neighbors: 1,
             epsilon: NUMERIC_DIGIT_PRECISION,
             hyperparameters: None,
+            context: TrainingContext::default(),
             show_voronoi: true,
         }
     }
@@ -39,10 +42,16 @@ impl ModelBuilder for EditedKNearestNeighborTrainer {
         Ok(())
     }
 
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
     fn build(
         &mut self,
         training_values: &[Box<[Numeric]>],
         target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
     ) -> Result<Box<dyn Model>, Box<dyn Error>> {
         if training_values.is_empty() {
             return Err("Empty training set given!".into());
@@ -68,35 +77,38 @@ impl ModelBuilder for EditedKNearestNeighborTrainer {
         }) / (training_values.len() - 1) as f64;
 
         // Generate model using internal parameters
-        let mut model = KNearestNeighbor {
-            num_neighbors: self.num_neighbors,
-            label_index: target_value_idx,
+        let mut model = KNearestNeighbor::new(
             label_examples,
-            epsilon: self.epsilon,
-            gamma: training_value_variance.sqrt(),
-        };
+            target_value_idx,
+            self.num_neighbors,
+            self.epsilon,
+            training_value_variance.sqrt(),
+        );
 
         if let Some(hyperparameters) = self.hyperparameters.as_ref() {
             model.set_hyperparameters(hyperparameters)?;
         }
+        model.fit(training_values, &self.context)?;
 
         // Predict values and if the label doesn't match add the input value to the set
+        let mut active = vec![true; training_values.len()];
         for (idx, sample) in training_values.iter().enumerate().rev() {
             // Remove current sample from list of label examples
-            model.label_examples.remove(idx);
+            active[idx] = false;
 
             // Predict value of current sample with the rest of the data set
-            let prediction = model.predict(sample);
+            let prediction = model.predict_among(sample, &active);
 
             if (prediction - sample[model.label_index]).abs() > self.epsilon {
                 // Sample was predicted incorrectly, therefore the sample is essential to the set
                 // and we must add it back to the set
-                model.label_examples.push(sample.clone());
+                active[idx] = true;
             }
             else {
                 println!("Sample {} was removed!", idx);
             }
         }
+        model.retain_examples(&active)?;
 
         if self.show_voronoi {
             model.generate_voronoi_diagram()?;
@@ -0,0 +1,231 @@
+// knn_radius.rs
+
+//! This file implements the logic to train a radius-based nearest neighbor learner. Every training
+//! example within a fixed radius of the sample takes part in the vote or in the kernel smoothing,
+//! instead of a fixed number of neighbors. The radius is in the same units as the configured
+//! distance, so it is a squared radius for the default squared euclidean distance. When no example
+//! falls inside the radius the model falls back to the single nearest neighbor or to the answer
+//! of the null model.
+
+use super::knn::KNearestNeighbor;
+use super::null::{NullClassificationModelTrainer, NullRegressionModelTrainer};
+use super::Model;
+use super::ModelBuilder;
+use super::TrainingContext;
+
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Fallback {
+    Nearest,
+    Null,
+}
+
+impl Fallback {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "nearest" => Ok(Self::Nearest),
+            "null" => Ok(Self::Null),
+            _ => Err(format!("Unsupported radius fallback: {}", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Nearest => "nearest",
+            Self::Null => "null",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RadiusNearestNeighbor {
+    // Answers the queries, asks for the single nearest neighbor when used as fallback
+    knn: KNearestNeighbor,
+    pub radius: Numeric,
+    pub fallback: Fallback,
+    null_classifier: Box<dyn Model>,
+    null_regression: Box<dyn Model>,
+}
+
+impl fmt::Debug for RadiusNearestNeighbor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RadiusNearestNeighbor")
+            .field("knn", &self.knn)
+            .field("radius", &self.radius)
+            .field("fallback", &self.fallback)
+            .finish()
+    }
+}
+
+impl Model for RadiusNearestNeighbor {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        let neighbors = self.knn.within(sample, self.radius);
+        if !neighbors.is_empty() {
+            return self.knn.vote(&neighbors);
+        }
+        println!("No neighbors within radius {}, using {} fallback", self.radius, self.fallback);
+        match self.fallback {
+            Fallback::Nearest => self.knn.label(sample),
+            Fallback::Null => self.null_classifier.label(sample),
+        }
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        let neighbors = self.knn.within(sample, self.radius);
+        if !neighbors.is_empty() {
+            return self.knn.smooth(&neighbors);
+        }
+        println!("No neighbors within radius {}, using {} fallback", self.radius, self.fallback);
+        match self.fallback {
+            Fallback::Nearest => self.knn.predict(sample),
+            Fallback::Null => self.null_regression.predict(sample),
+        }
+    }
+
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        let neighbors = self.knn.within(sample, self.radius);
+        if !neighbors.is_empty() {
+            return self.knn.vote_shares(&neighbors);
+        }
+        match self.fallback {
+            Fallback::Nearest => self.knn.predict_proba(sample),
+            Fallback::Null => self.null_classifier.predict_proba(sample),
+        }
+    }
+
+    fn type_id(&self) -> &'static str {
+        "RadiusNearestNeighbor"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        // The number of neighbors is always one, it is only used by the nearest fallback
+        let mut ret = self.knn.get_hyperparameters();
+        ret.remove("num_neighbors");
+        ret.insert("radius".into(), self.radius.to_string());
+        ret.insert("fallback".into(), self.fallback.to_string());
+        ret
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "radius" => {
+                    self.radius = val.parse::<Numeric>()?;
+                }
+                "fallback" => {
+                    self.fallback = Fallback::from_name(val)?;
+                }
+                _ => {}
+            }
+        }
+
+        // The remaining hyperparameters belong to the nearest neighbor model, except for the
+        // number of neighbors which the radius replaces
+        let mut knn_hyperparameters = hyperparameters.clone();
+        knn_hyperparameters.remove("num_neighbors");
+        self.knn.set_hyperparameters(&knn_hyperparameters)
+    }
+}
+
+pub struct RadiusNearestNeighborTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+    radius: Numeric,
+    epsilon: f64,
+}
+
+impl ModelBuilder for RadiusNearestNeighborTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+            radius: 1.0,
+            epsilon: NUMERIC_DIGIT_PRECISION,
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        // Calculate training value mean
+        let training_value_mean = training_values.iter().fold(0.0, |acc, x| {
+            acc + x[target_value_idx]
+        }) / training_values.len() as f64;
+
+        // Calculate training value variance
+        let training_value_variance = training_values.iter().fold(0.0, |acc, sample| {
+            acc + (sample[target_value_idx] - training_value_mean).powi(2)
+        }) / (training_values.len() - 1) as f64;
+
+        // Build the null models answering when no example is inside the radius
+        let mut null_classifier = NullClassificationModelTrainer::new();
+        null_classifier.with_context(&self.context)?;
+        let null_classifier = null_classifier.build(training_values, target_value_idx, None)?;
+        let null_regression =
+            NullRegressionModelTrainer::new().build(training_values, target_value_idx, None)?;
+
+        // Build the nearest neighbor model with all the training values as label examples
+        let mut model = RadiusNearestNeighbor {
+            knn: KNearestNeighbor::new(
+                training_values.to_vec(),
+                target_value_idx,
+                1,
+                self.epsilon,
+                training_value_variance.sqrt(),
+            ),
+            radius: self.radius,
+            fallback: Fallback::Nearest,
+            null_classifier,
+            null_regression,
+        };
+
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.knn.fit(training_values, &self.context)?;
+
+        println!("Built model: {:?}", model);
+
+        Ok(Box::new(model))
+    }
+}
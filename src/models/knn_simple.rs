@@ -5,6 +5,7 @@
 
 use super::Model;
 use super::ModelBuilder;
+use super::TrainingContext;
 
 use crate::models::knn::KNearestNeighbor;
 use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
@@ -14,6 +15,7 @@ use std::error::Error;
 
 pub struct SimpleKNearestNeighborTrainer {
     hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
     num_neighbors: usize,
     epsilon: f64,
     show_voronoi: bool,
@@ -26,6 +28,7 @@ impl ModelBuilder for SimpleKNearestNeighborTrainer {
     {
         Self {
             hyperparameters: None,
+            context: TrainingContext::default(),
             num_neighbors: 1,
             epsilon: NUMERIC_DIGIT_PRECISION,
             show_voronoi: false,
@@ -40,11 +43,21 @@ impl ModelBuilder for SimpleKNearestNeighborTrainer {
         Ok(())
     }
 
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn supports_sample_weights(&self) -> bool {
+        true
+    }
+
     fn build(
         &mut self,
         training_values: &[Box<[Numeric]>],
         target_value_idx: usize,
-        ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
         if training_values.is_empty() {
             return Err("Empty training set given!".into());
         }
@@ -69,17 +82,21 @@ impl ModelBuilder for SimpleKNearestNeighborTrainer {
         }) / (training_values.len() - 1) as f64;
 
         // Build k-nearest neighbors model with the label examples
-        let mut model = KNearestNeighbor {
-            num_neighbors: self.num_neighbors,
-            label_index: target_value_idx,
+        let mut model = KNearestNeighbor::new(
             label_examples,
-            epsilon: self.epsilon,
-            gamma: training_value_variance.sqrt(),
-        };
+            target_value_idx,
+            self.num_neighbors,
+            self.epsilon,
+            training_value_variance.sqrt(),
+        );
 
         if let Some(hyperparameters) = self.hyperparameters.as_ref() {
             model.set_hyperparameters(hyperparameters)?;
         }
+        if let Some(sample_weights) = sample_weights {
+            model.set_example_weights(sample_weights)?;
+        }
+        model.fit(training_values, &self.context)?;
 
         println!("Built model: {:?}", model);
 
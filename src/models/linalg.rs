@@ -0,0 +1,68 @@
+// linalg.rs
+
+//! This file implements the small amount of linear algebra the parametric models need: building
+//! the normal equations of a least squares problem and solving a square linear system.
+
+use crate::types::Numeric;
+
+use std::error::Error;
+
+// Pivots smaller than this are treated as zero, meaning the system has no unique solution
+const SINGULAR_PIVOT: f64 = 1e-12;
+
+/// Returns the normal equations (XᵀX, Xᵀy) of the least squares problem Xw = y, where the rows of
+/// X are given by `rows`
+pub fn normal_equations(rows: &[Vec<Numeric>], targets: &[Numeric]) -> (Vec<Vec<f64>>, Vec<f64>) {
+    let num_columns = rows.first().map_or(0, |row| row.len());
+    let mut gram = vec![vec![0.0; num_columns]; num_columns];
+    let mut moments = vec![0.0; num_columns];
+    for (row, target) in rows.iter().zip(targets.iter()) {
+        for ((gram_row, moment), value) in gram.iter_mut().zip(moments.iter_mut()).zip(row.iter()) {
+            *moment += value * target;
+            for (cell, other) in gram_row.iter_mut().zip(row.iter()) {
+                *cell += value * other;
+            }
+        }
+    }
+    (gram, moments)
+}
+
+/// Solves the square system Ax = b by Gaussian elimination with partial pivoting
+pub fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Result<Vec<f64>, Box<dyn Error>> {
+    let size = rhs.len();
+    if matrix.len() != size || matrix.iter().any(|row| row.len() != size) {
+        return Err("The linear system is not square!".into());
+    }
+
+    // Reduce the matrix to upper triangular form
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|&x, &y| matrix[x][column].abs().total_cmp(&matrix[y][column].abs()))
+            .unwrap();
+        if matrix[pivot][column].abs() < SINGULAR_PIVOT {
+            return Err("The linear system is singular!".into());
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+
+        for row in (column + 1)..size {
+            let factor = matrix[row][column] / matrix[column][column];
+            if factor == 0.0 {
+                continue;
+            }
+            let (upper, lower) = matrix.split_at_mut(row);
+            for (cell, pivot_value) in lower[0].iter_mut().zip(upper[column].iter()).skip(column) {
+                *cell -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+
+    // Back substitution
+    let mut solution = vec![0.0; size];
+    for row in (0..size).rev() {
+        let sum = ((row + 1)..size).fold(0.0, |acc, k| acc + matrix[row][k] * solution[k]);
+        solution[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+    Ok(solution)
+}
@@ -0,0 +1,330 @@
+// linear_regression.rs
+
+//! This file implements linear regression models. Ordinary least squares and ridge regression are
+//! solved in closed form from the normal equations, lasso regression is solved by coordinate
+//! descent. The features and the target are centered before fitting so the intercept is never
+//! penalized. Following the usual conventions, ridge minimizes ||y - Xw||² + alpha·||w||² and lasso
+//! minimizes ||y - Xw||² / 2n + alpha·||w||₁. The learned coefficients are reported with the names
+//! of their columns in the model hyper-parameters.
+
+use super::linalg;
+use super::{Model, ModelBuilder, TrainingContext};
+
+use crate::types::Numeric;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Penalty {
+    None,
+    Ridge,
+    Lasso,
+}
+
+impl fmt::Display for Penalty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::None => "none",
+            Self::Ridge => "ridge",
+            Self::Lasso => "lasso",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LinearRegression {
+    pub label_index: usize,
+    pub penalty: Penalty,
+    pub alpha: f64,
+    pub max_iterations: usize,
+    pub tolerance: f64,
+    intercept: f64,
+    // Coefficient of each feature column, in column order skipping the label
+    coefficients: Vec<f64>,
+    column_names: Vec<String>,
+}
+
+impl Model for LinearRegression {
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        self.features(sample)
+            .zip(self.coefficients.iter())
+            .fold(self.intercept, |acc, (value, coefficient)| acc + value * coefficient)
+    }
+
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        self.predict(sample)
+    }
+
+    fn type_id(&self) -> &'static str {
+        "LinearRegression"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        let mut ret = HashMap::from([
+            ("penalty".into(), self.penalty.to_string()),
+            ("alpha".into(), self.alpha.to_string()),
+            ("max_iterations".into(), self.max_iterations.to_string()),
+            ("tolerance".into(), self.tolerance.to_string()),
+            ("intercept".into(), self.intercept.to_string()),
+        ]);
+        for (name, coefficient) in self.column_names.iter().zip(self.coefficients.iter()) {
+            ret.insert(format!("coefficient[{}]", name), coefficient.to_string());
+        }
+        ret
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "alpha" => {
+                    let alpha = val.parse::<f64>()?;
+                    if alpha < 0.0 {
+                        return Err("The regularization strength can't be negative!".into());
+                    }
+                    self.alpha = alpha;
+                }
+                "max_iterations" => {
+                    self.max_iterations = val.parse::<usize>()?;
+                }
+                "tolerance" => {
+                    self.tolerance = val.parse::<f64>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LinearRegression {
+    pub fn new(label_index: usize, penalty: Penalty) -> Self {
+        Self {
+            label_index,
+            penalty,
+            alpha: 1.0,
+            max_iterations: 1000,
+            tolerance: 1e-6,
+            intercept: 0.0,
+            coefficients: Vec::new(),
+            column_names: Vec::new(),
+        }
+    }
+
+    /// Fits the coefficients to the training values. Must be called after the hyperparameters are
+    /// set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        let num_columns = training_values[0].len();
+        self.column_names = (0..num_columns)
+            .filter(|&column| column != self.label_index)
+            .map(|column| context.column_name(column))
+            .collect();
+
+        // Center the features and the target
+        let num_samples = training_values.len() as f64;
+        let rows = training_values
+            .iter()
+            .map(|sample| self.features(sample).collect::<Vec<Numeric>>())
+            .collect::<Vec<Vec<Numeric>>>();
+        let targets = training_values
+            .iter()
+            .map(|sample| sample[self.label_index])
+            .collect::<Vec<Numeric>>();
+        let feature_means = (0..num_columns - 1)
+            .map(|feature| rows.iter().map(|row| row[feature]).sum::<f64>() / num_samples)
+            .collect::<Vec<f64>>();
+        let target_mean = targets.iter().sum::<f64>() / num_samples;
+        let centered_rows = rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(feature_means.iter())
+                    .map(|(value, mean)| value - mean)
+                    .collect()
+            })
+            .collect::<Vec<Vec<Numeric>>>();
+        let centered_targets = targets
+            .iter()
+            .map(|target| target - target_mean)
+            .collect::<Vec<Numeric>>();
+
+        self.coefficients = match self.penalty {
+            Penalty::None => {
+                let (gram, moments) = linalg::normal_equations(&centered_rows, &centered_targets);
+                linalg::solve(gram, moments).map_err(|_| {
+                    "The features are collinear, least squares has no unique solution! Try ridge regression."
+                })?
+            }
+            Penalty::Ridge => {
+                let (mut gram, moments) =
+                    linalg::normal_equations(&centered_rows, &centered_targets);
+                for (feature, row) in gram.iter_mut().enumerate() {
+                    row[feature] += self.alpha;
+                }
+                linalg::solve(gram, moments)?
+            }
+            Penalty::Lasso => self.coordinate_descent(&centered_rows, &centered_targets),
+        };
+
+        self.intercept = target_mean
+            - feature_means
+                .iter()
+                .zip(self.coefficients.iter())
+                .fold(0.0, |acc, (mean, coefficient)| acc + mean * coefficient);
+
+        Ok(())
+    }
+
+    // Minimizes the lasso objective one coefficient at a time until no coefficient moves more than
+    // the tolerance
+    fn coordinate_descent(&self, rows: &[Vec<Numeric>], targets: &[Numeric]) -> Vec<f64> {
+        let num_samples = rows.len() as f64;
+        let num_features = rows.first().map_or(0, |row| row.len());
+        let squared_norms = (0..num_features)
+            .map(|feature| rows.iter().map(|row| row[feature].powi(2)).sum::<f64>() / num_samples)
+            .collect::<Vec<f64>>();
+
+        let mut coefficients = vec![0.0; num_features];
+        let mut residuals = targets.to_vec();
+        for iteration in 0..self.max_iterations {
+            let mut max_change: f64 = 0.0;
+            for feature in 0..num_features {
+                if squared_norms[feature] == 0.0 {
+                    continue;
+                }
+
+                // Correlation of the feature with the residual left by every other feature
+                let rho = rows
+                    .iter()
+                    .zip(residuals.iter())
+                    .map(|(row, residual)| row[feature] * (residual + row[feature] * coefficients[feature]))
+                    .sum::<f64>()
+                    / num_samples;
+                let updated = soft_threshold(rho, self.alpha) / squared_norms[feature];
+
+                let change = updated - coefficients[feature];
+                if change != 0.0 {
+                    for (row, residual) in rows.iter().zip(residuals.iter_mut()) {
+                        *residual -= row[feature] * change;
+                    }
+                    coefficients[feature] = updated;
+                }
+                max_change = max_change.max(change.abs());
+            }
+
+            if max_change < self.tolerance {
+                println!("Coordinate descent converged after {} iterations", iteration + 1);
+                return coefficients;
+            }
+        }
+        println!(
+            "Coordinate descent stopped after {} iterations without converging",
+            self.max_iterations
+        );
+        coefficients
+    }
+
+    // Iterates over the feature values of a sample, skipping the label
+    fn features<'a>(&self, sample: &'a [Numeric]) -> impl Iterator<Item = Numeric> + 'a {
+        let label_index = self.label_index;
+        sample
+            .iter()
+            .enumerate()
+            .filter(move |&(column, _)| column != label_index)
+            .map(|(_, value)| *value)
+    }
+}
+
+fn soft_threshold(value: f64, threshold: f64) -> f64 {
+    if value > threshold {
+        value - threshold
+    } else if value < -threshold {
+        value + threshold
+    } else {
+        0.0
+    }
+}
+
+pub struct LinearRegressionTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+    penalty: Penalty,
+}
+
+impl LinearRegressionTrainer {
+    pub fn ridge() -> Self {
+        Self {
+            penalty: Penalty::Ridge,
+            ..Self::new()
+        }
+    }
+
+    pub fn lasso() -> Self {
+        Self {
+            penalty: Penalty::Lasso,
+            ..Self::new()
+        }
+    }
+}
+
+impl ModelBuilder for LinearRegressionTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+            penalty: Penalty::None,
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = LinearRegression::new(target_value_idx, self.penalty);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!("Built model: {:?}", model);
+
+        Ok(Box::new(model))
+    }
+}
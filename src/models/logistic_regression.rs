@@ -0,0 +1,389 @@
+// logistic_regression.rs
+
+//! This file implements linear classifiers trained by gradient descent on the log loss. Binary
+//! logistic regression models the probability of the second class with a sigmoid of a linear
+//! score, while softmax regression keeps one linear score per class and turns them into class
+//! probabilities with the softmax function. Training runs over the training values in epochs,
+//! either in a single batch or in shuffled mini-batches, with an optional L2 penalty on the
+//! weights, and stops early when the loss changes less than the tolerance between epochs. The
+//! mini-batches are shuffled by a seeded random number generator so runs can be reproduced.
+//! Gradient descent works best with features on similar scales, e.g. after a z-score transform.
+
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+
+const MIN_PROBABILITY: f64 = 1e-15;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogisticRegression {
+    pub label_index: usize,
+    // One score per class (softmax) instead of a single score for the second class (logistic)
+    pub multinomial: bool,
+    pub learning_rate: f64,
+    pub epochs: usize,
+    // Number of training values per gradient step, the whole training set when missing
+    pub batch_size: Option<usize>,
+    pub l2: f64,
+    pub tolerance: f64,
+    pub seed: u64,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    // Weights of each output score over the features, in column order skipping the label
+    weights: Vec<Vec<f64>>,
+    biases: Vec<f64>,
+}
+
+impl Model for LogisticRegression {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        // Return the class with the largest probability, ties go to the smallest class
+        let probabilities = self.probabilities(&self.features(sample));
+        let best = probabilities
+            .iter()
+            .enumerate()
+            .fold(0, |best, (position, &probability)| {
+                if probability > probabilities[best] {
+                    position
+                } else {
+                    best
+                }
+            });
+        self.classes[best]
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        self.label(sample)
+    }
+
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        self.classes
+            .iter()
+            .copied()
+            .zip(self.probabilities(&self.features(sample)))
+            .collect()
+    }
+
+    fn type_id(&self) -> &'static str {
+        "LogisticRegression"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("learning_rate".into(), self.learning_rate.to_string()),
+            ("epochs".into(), self.epochs.to_string()),
+            (
+                "batch_size".into(),
+                self.batch_size
+                    .map_or("full".to_string(), |batch_size| batch_size.to_string()),
+            ),
+            ("l2".into(), self.l2.to_string()),
+            ("tolerance".into(), self.tolerance.to_string()),
+            ("seed".into(), self.seed.to_string()),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "learning_rate" => {
+                    self.learning_rate = val.parse::<f64>()?;
+                }
+                "epochs" => {
+                    self.epochs = val.parse::<usize>()?;
+                }
+                "batch_size" => {
+                    self.batch_size = match val.as_str() {
+                        "full" => None,
+                        _ => Some(val.parse::<usize>()?.max(1)),
+                    };
+                }
+                "l2" => {
+                    self.l2 = val.parse::<f64>()?;
+                }
+                "tolerance" => {
+                    self.tolerance = val.parse::<f64>()?;
+                }
+                "seed" => {
+                    self.seed = val.parse::<u64>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LogisticRegression {
+    pub fn new(label_index: usize, multinomial: bool) -> Self {
+        Self {
+            label_index,
+            multinomial,
+            learning_rate: 0.1,
+            epochs: 100,
+            batch_size: None,
+            l2: 0.0,
+            tolerance: 1e-6,
+            seed: 0,
+            classes: Vec::new(),
+            weights: Vec::new(),
+            biases: Vec::new(),
+        }
+    }
+
+    /// Trains the weights on the training values. Must be called after the hyperparameters are
+    /// set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+        if self.classes.len() < 2 {
+            return Err("A linear classifier needs at least two classes!".into());
+        }
+        if !self.multinomial && self.classes.len() > 2 {
+            return Err(
+                "Logistic regression only supports two classes, use softmax regression instead!"
+                    .into(),
+            );
+        }
+
+        let rows = training_values
+            .iter()
+            .map(|sample| self.features(sample))
+            .collect::<Vec<Vec<Numeric>>>();
+        let targets = training_values
+            .iter()
+            .map(|sample| self.targets(sample[self.label_index]))
+            .collect::<Vec<Vec<f64>>>();
+
+        let num_outputs = if self.multinomial { self.classes.len() } else { 1 };
+        let num_features = rows[0].len();
+        self.weights = vec![vec![0.0; num_features]; num_outputs];
+        self.biases = vec![0.0; num_outputs];
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut indexes = (0..rows.len()).collect::<Vec<usize>>();
+        let batch_size = self.batch_size.unwrap_or(rows.len());
+        let mut previous_loss = Numeric::INFINITY;
+        for epoch in 0..self.epochs {
+            if batch_size < rows.len() {
+                indexes.shuffle(&mut rng);
+            }
+
+            for batch in indexes.chunks(batch_size) {
+                self.gradient_step(&rows, &targets, batch);
+            }
+
+            // Stop when the loss stops changing
+            let loss = self.loss(&rows, training_values);
+            println!("Epoch {}: loss: {}", epoch, loss);
+            if (previous_loss - loss).abs() < self.tolerance {
+                println!("Converged after {} epochs", epoch + 1);
+                break;
+            }
+            previous_loss = loss;
+        }
+
+        Ok(())
+    }
+
+    // Moves the weights against the mean gradient of the log loss over the batch
+    fn gradient_step(&mut self, rows: &[Vec<Numeric>], targets: &[Vec<f64>], batch: &[usize]) {
+        let num_features = rows[0].len();
+        let mut weight_gradients = vec![vec![0.0; num_features]; self.weights.len()];
+        let mut bias_gradients = vec![0.0; self.biases.len()];
+        for &idx in batch.iter() {
+            let outputs = self.outputs(&rows[idx]);
+            for (output, (value, target)) in outputs.iter().zip(targets[idx].iter()).enumerate() {
+                let error = value - target;
+                bias_gradients[output] += error;
+                for (gradient, feature) in weight_gradients[output].iter_mut().zip(rows[idx].iter()) {
+                    *gradient += error * feature;
+                }
+            }
+        }
+
+        let batch_size = batch.len() as f64;
+        for (weights, gradients) in self.weights.iter_mut().zip(weight_gradients.iter()) {
+            for (weight, gradient) in weights.iter_mut().zip(gradients.iter()) {
+                *weight -= self.learning_rate * (gradient / batch_size + self.l2 * *weight);
+            }
+        }
+        for (bias, gradient) in self.biases.iter_mut().zip(bias_gradients.iter()) {
+            *bias -= self.learning_rate * gradient / batch_size;
+        }
+    }
+
+    // Mean log loss over the training values plus the L2 penalty
+    fn loss(&self, rows: &[Vec<Numeric>], training_values: &[Box<[Numeric]>]) -> f64 {
+        let log_loss = rows
+            .iter()
+            .zip(training_values.iter())
+            .map(|(row, sample)| {
+                let target = sample[self.label_index];
+                let probability = self
+                    .classes
+                    .iter()
+                    .zip(self.probabilities(row))
+                    .find(|(class, _)| (*class - target).abs() < NUMERIC_DIGIT_PRECISION)
+                    .map_or(0.0, |(_, probability)| probability);
+                -probability.clamp(MIN_PROBABILITY, 1.0).ln()
+            })
+            .sum::<f64>()
+            / rows.len() as f64;
+        let penalty = self
+            .weights
+            .iter()
+            .flatten()
+            .map(|weight| weight * weight)
+            .sum::<f64>()
+            * self.l2
+            / 2.0;
+        log_loss + penalty
+    }
+
+    // Returns the values the outputs are trained towards: one-hot class indicators for softmax,
+    // whether the sample is of the second class for logistic regression
+    fn targets(&self, label: Numeric) -> Vec<f64> {
+        let is_class = |class: &Numeric| {
+            if (label - class).abs() < NUMERIC_DIGIT_PRECISION {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        if self.multinomial {
+            self.classes.iter().map(is_class).collect()
+        } else {
+            vec![is_class(&self.classes[1])]
+        }
+    }
+
+    // Returns the probability of each output: softmax of the scores, or sigmoid of the single score
+    fn outputs(&self, features: &[Numeric]) -> Vec<f64> {
+        let scores = self
+            .weights
+            .iter()
+            .zip(self.biases.iter())
+            .map(|(weights, bias)| {
+                weights
+                    .iter()
+                    .zip(features.iter())
+                    .fold(*bias, |acc, (weight, feature)| acc + weight * feature)
+            })
+            .collect::<Vec<f64>>();
+
+        if !self.multinomial {
+            return vec![1.0 / (1.0 + (-scores[0]).exp())];
+        }
+
+        // Subtract the largest score so the exponentials can't overflow
+        let max_score = scores.iter().copied().fold(Numeric::NEG_INFINITY, Numeric::max);
+        let exponentials = scores
+            .iter()
+            .map(|score| (score - max_score).exp())
+            .collect::<Vec<f64>>();
+        let total = exponentials.iter().sum::<f64>();
+        exponentials.iter().map(|value| value / total).collect()
+    }
+
+    // Returns the probability of each class
+    fn probabilities(&self, features: &[Numeric]) -> Vec<f64> {
+        let outputs = self.outputs(features);
+        if self.multinomial {
+            outputs
+        } else {
+            vec![1.0 - outputs[0], outputs[0]]
+        }
+    }
+
+    // Returns the feature values of a sample, skipping the label
+    fn features(&self, sample: &[Numeric]) -> Vec<Numeric> {
+        sample
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| column != self.label_index)
+            .map(|(_, value)| *value)
+            .collect()
+    }
+}
+
+pub struct LogisticRegressionTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+    multinomial: bool,
+}
+
+impl LogisticRegressionTrainer {
+    pub fn softmax() -> Self {
+        Self {
+            multinomial: true,
+            ..Self::new()
+        }
+    }
+}
+
+impl ModelBuilder for LogisticRegressionTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+            multinomial: false,
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = LogisticRegression::new(target_value_idx, self.multinomial);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!("Built model: {:?}", model);
+
+        Ok(Box::new(model))
+    }
+}
@@ -0,0 +1,323 @@
+// lvq.rs
+
+//! This file implements Learning Vector Quantization (LVQ) classifiers. The model keeps a fixed
+//! number of prototypes per class, initialized on random training values of the class, and labels
+//! a sample with the class of its closest prototype. Training presents the training values in a
+//! shuffled order every epoch with a learning rate that decays linearly to zero. LVQ1 moves the
+//! closest prototype towards the sample when their classes match and away from it otherwise.
+//! LVQ2.1 looks at the two closest prototypes and, when exactly one of them has the class of the
+//! sample and the sample falls in a window around the midplane between them, moves the right one
+//! towards the sample and the wrong one away from it. Distances are Euclidean over the features.
+
+use super::kmeans::squared_distance;
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Variant {
+    Lvq1,
+    Lvq21,
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Lvq1 => "lvq1",
+            Self::Lvq21 => "lvq2.1",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LearningVectorQuantization {
+    pub label_index: usize,
+    pub variant: Variant,
+    pub prototypes_per_class: usize,
+    pub learning_rate: f64,
+    pub epochs: usize,
+    // Relative width of the LVQ2.1 window around the midplane of the two closest prototypes
+    pub window: f64,
+    pub seed: u64,
+    // Feature values of every prototype, in column order skipping the label, and its class
+    prototypes: Vec<(Vec<Numeric>, Numeric)>,
+}
+
+impl Model for LearningVectorQuantization {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        let features = self.features(sample);
+        let (closest, _) = self.closest(&features);
+        self.prototypes[closest[0]].1
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        self.label(sample)
+    }
+
+    fn type_id(&self) -> &'static str {
+        "LearningVectorQuantization"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("variant".into(), self.variant.to_string()),
+            ("prototypes_per_class".into(), self.prototypes_per_class.to_string()),
+            ("learning_rate".into(), self.learning_rate.to_string()),
+            ("epochs".into(), self.epochs.to_string()),
+            ("window".into(), self.window.to_string()),
+            ("seed".into(), self.seed.to_string()),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "prototypes_per_class" => {
+                    self.prototypes_per_class = val.parse::<usize>()?;
+                    if self.prototypes_per_class == 0 {
+                        return Err("Every class needs at least one prototype!".into());
+                    }
+                }
+                "learning_rate" => {
+                    self.learning_rate = val.parse::<f64>()?;
+                }
+                "epochs" => {
+                    self.epochs = val.parse::<usize>()?;
+                }
+                "window" => {
+                    self.window = val.parse::<f64>()?;
+                    if !(0.0..1.0).contains(&self.window) {
+                        return Err("The window must be in [0, 1)!".into());
+                    }
+                }
+                "seed" => {
+                    self.seed = val.parse::<u64>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LearningVectorQuantization {
+    pub fn new(label_index: usize, variant: Variant) -> Self {
+        Self {
+            label_index,
+            variant,
+            prototypes_per_class: 1,
+            learning_rate: 0.1,
+            epochs: 30,
+            window: 0.3,
+            seed: 0,
+            prototypes: Vec::new(),
+        }
+    }
+
+    /// Places the prototypes and moves them over the training epochs. Must be called after the
+    /// hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        let rows = training_values
+            .iter()
+            .map(|sample| self.features(sample))
+            .collect::<Vec<Vec<Numeric>>>();
+        let labels = training_values
+            .iter()
+            .map(|sample| sample[self.label_index])
+            .collect::<Vec<Numeric>>();
+
+        // Start the prototypes of every class on random training values of the class
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+        self.prototypes.clear();
+        for class in classes {
+            let members = labels
+                .iter()
+                .enumerate()
+                .filter(|(_, label)| (*label - class).abs() < NUMERIC_DIGIT_PRECISION)
+                .map(|(idx, _)| idx)
+                .collect::<Vec<usize>>();
+            for &idx in members.choose_multiple(&mut rng, self.prototypes_per_class) {
+                self.prototypes.push((rows[idx].clone(), class));
+            }
+        }
+        if self.prototypes.len() < 2 {
+            return Err("LVQ needs at least two prototypes!".into());
+        }
+
+        // Samples inside the window satisfy min(d₁/d₂, d₂/d₁) > s
+        let threshold = (1.0 - self.window) / (1.0 + self.window);
+        let mut order = (0..rows.len()).collect::<Vec<usize>>();
+        for epoch in 0..self.epochs {
+            let rate = self.learning_rate * (1.0 - epoch as f64 / self.epochs as f64);
+            order.shuffle(&mut rng);
+            for &idx in order.iter() {
+                let (closest, distances) = self.closest(&rows[idx]);
+                let matches = closest.map(|prototype| {
+                    (self.prototypes[prototype].1 - labels[idx]).abs() < NUMERIC_DIGIT_PRECISION
+                });
+                match self.variant {
+                    Variant::Lvq1 => {
+                        let direction = if matches[0] { 1.0 } else { -1.0 };
+                        self.shift(closest[0], &rows[idx], direction * rate);
+                    }
+                    Variant::Lvq21 => {
+                        if matches[0] == matches[1] {
+                            continue;
+                        }
+                        let (near, far) = (distances[0].sqrt(), distances[1].sqrt());
+                        if far > 0.0 && near / far <= threshold {
+                            continue;
+                        }
+                        let (right, wrong) = if matches[0] {
+                            (closest[0], closest[1])
+                        } else {
+                            (closest[1], closest[0])
+                        };
+                        self.shift(right, &rows[idx], rate);
+                        self.shift(wrong, &rows[idx], -rate);
+                    }
+                }
+            }
+
+            let errors = rows
+                .iter()
+                .zip(labels.iter())
+                .filter(|(row, label)| {
+                    let (closest, _) = self.closest(row);
+                    (self.prototypes[closest[0]].1 - *label).abs() >= NUMERIC_DIGIT_PRECISION
+                })
+                .count();
+            println!(
+                "Epoch {}: training error: {}",
+                epoch,
+                errors as f64 / rows.len() as f64
+            );
+        }
+
+        Ok(())
+    }
+
+    // Returns the positions of the two closest prototypes and their squared distances
+    fn closest(&self, features: &[Numeric]) -> ([usize; 2], [f64; 2]) {
+        let mut closest = [0, 0];
+        let mut distances = [f64::INFINITY, f64::INFINITY];
+        for (position, (prototype, _)) in self.prototypes.iter().enumerate() {
+            let distance = squared_distance(prototype, features);
+            if distance < distances[0] {
+                closest = [position, closest[0]];
+                distances = [distance, distances[0]];
+            } else if distance < distances[1] {
+                closest[1] = position;
+                distances[1] = distance;
+            }
+        }
+        (closest, distances)
+    }
+
+    // Moves a prototype towards the sample by the given rate, away from it when the rate is negative
+    fn shift(&mut self, prototype: usize, features: &[Numeric], rate: f64) {
+        for (value, feature) in self.prototypes[prototype].0.iter_mut().zip(features.iter()) {
+            *value += rate * (feature - *value);
+        }
+    }
+
+    // Returns the feature values of a sample, skipping the label
+    fn features(&self, sample: &[Numeric]) -> Vec<Numeric> {
+        sample
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| column != self.label_index)
+            .map(|(_, value)| *value)
+            .collect()
+    }
+}
+
+pub struct LearningVectorQuantizationTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+    variant: Variant,
+}
+
+impl LearningVectorQuantizationTrainer {
+    pub fn lvq21() -> Self {
+        Self {
+            variant: Variant::Lvq21,
+            ..Self::new()
+        }
+    }
+}
+
+impl ModelBuilder for LearningVectorQuantizationTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+            variant: Variant::Lvq1,
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = LearningVectorQuantization::new(target_value_idx, self.variant);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built {} model with {} prototypes",
+            model.variant,
+            model.prototypes.len()
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -0,0 +1,714 @@
+// mlp.rs
+
+//! This file implements a feed-forward neural network (multilayer perceptron) trained with
+//! backpropagation. The network is a stack of fully connected layers: the hidden layers share one
+//! activation function and the output layer follows the model task, a softmax over the classes
+//! for classification or a single linear unit for regression. Training minimizes the cross
+//! entropy (classification) or the squared error (regression) by mini-batch gradient descent with
+//! momentum and weight decay. The regression target is standardized before training and restored
+//! when predicting. Like the other gradient based models, the network works best with features on
+//! similar scales, e.g. after a z-score transform.
+//!
+//! The hidden layers can be pretrained as the encoder of an autoencoder whose bottleneck is the
+//! last hidden layer, the supervised training then starts from the encoder weights.
+//!
+//! The layers, the network and the gradient descent settings are public so other neural models
+//! can be assembled from them.
+
+use super::autoencoder::Autoencoder;
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+const MIN_PROBABILITY: f64 = 1e-15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Activation {
+    Sigmoid,
+    Tanh,
+    Relu,
+    Linear,
+    // Only meaningful on the output layer, where it is paired with the cross entropy
+    Softmax,
+}
+
+impl Activation {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "sigmoid" => Ok(Self::Sigmoid),
+            "tanh" => Ok(Self::Tanh),
+            "relu" => Ok(Self::Relu),
+            "linear" => Ok(Self::Linear),
+            _ => Err(format!("Invalid activation {}, expected sigmoid, tanh or relu", name).into()),
+        }
+    }
+
+    fn apply(&self, values: &mut [f64]) {
+        match self {
+            Self::Sigmoid => values.iter_mut().for_each(|value| *value = 1.0 / (1.0 + (-*value).exp())),
+            Self::Tanh => values.iter_mut().for_each(|value| *value = value.tanh()),
+            Self::Relu => values.iter_mut().for_each(|value| *value = value.max(0.0)),
+            Self::Linear => {}
+            Self::Softmax => {
+                // Subtract the largest value so the exponentials can't overflow
+                let max_value = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                values.iter_mut().for_each(|value| *value = (*value - max_value).exp());
+                let total = values.iter().sum::<f64>();
+                values.iter_mut().for_each(|value| *value /= total);
+            }
+        }
+    }
+
+    // Derivative of the activation, expressed in terms of its output
+    fn derivative(&self, output: f64) -> f64 {
+        match self {
+            Self::Sigmoid => output * (1.0 - output),
+            Self::Tanh => 1.0 - output * output,
+            Self::Relu => {
+                if output > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Linear | Self::Softmax => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Activation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Sigmoid => "sigmoid",
+            Self::Tanh => "tanh",
+            Self::Relu => "relu",
+            Self::Linear => "linear",
+            Self::Softmax => "softmax",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Loss {
+    SquaredError,
+    CrossEntropy,
+}
+
+/// A fully connected layer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Layer {
+    // One row of input weights per output unit
+    pub weights: Vec<Vec<f64>>,
+    pub biases: Vec<f64>,
+    pub activation: Activation,
+}
+
+impl Layer {
+    /// Creates a layer with Glorot uniform weights and zero biases
+    pub fn new(inputs: usize, outputs: usize, activation: Activation, rng: &mut StdRng) -> Self {
+        let limit = (6.0 / (inputs + outputs) as f64).sqrt();
+        Self {
+            weights: (0..outputs)
+                .map(|_| (0..inputs).map(|_| rng.gen_range(-limit..=limit)).collect())
+                .collect(),
+            biases: vec![0.0; outputs],
+            activation,
+        }
+    }
+
+    pub fn forward(&self, inputs: &[f64]) -> Vec<f64> {
+        let mut outputs = self
+            .weights
+            .iter()
+            .zip(self.biases.iter())
+            .map(|(weights, bias)| {
+                weights
+                    .iter()
+                    .zip(inputs.iter())
+                    .fold(*bias, |acc, (weight, input)| acc + weight * input)
+            })
+            .collect::<Vec<f64>>();
+        self.activation.apply(&mut outputs);
+        outputs
+    }
+}
+
+/// Settings of mini-batch gradient descent with momentum and weight decay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GradientDescent {
+    pub learning_rate: f64,
+    pub momentum: f64,
+    pub weight_decay: f64,
+    pub epochs: usize,
+    // Number of training values per gradient step, the whole training set when missing
+    pub batch_size: Option<usize>,
+}
+
+impl Default for GradientDescent {
+    fn default() -> Self {
+        Self {
+            learning_rate: 0.01,
+            momentum: 0.9,
+            weight_decay: 0.0,
+            epochs: 100,
+            batch_size: Some(32),
+        }
+    }
+}
+
+impl GradientDescent {
+    /// Sets the hyper-parameter if it belongs to gradient descent, returns whether it did
+    pub fn set_hyperparameter(&mut self, key: &str, val: &str) -> Result<bool, Box<dyn Error>> {
+        match key {
+            "learning_rate" => self.learning_rate = val.parse::<f64>()?,
+            "momentum" => {
+                self.momentum = val.parse::<f64>()?;
+                if !(0.0..1.0).contains(&self.momentum) {
+                    return Err("The momentum must be in [0, 1)!".into());
+                }
+            }
+            "weight_decay" => self.weight_decay = val.parse::<f64>()?,
+            "epochs" => self.epochs = val.parse::<usize>()?,
+            "batch_size" => {
+                self.batch_size = match val {
+                    "full" => None,
+                    _ => Some(val.parse::<usize>()?.max(1)),
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("learning_rate".into(), self.learning_rate.to_string()),
+            ("momentum".into(), self.momentum.to_string()),
+            ("weight_decay".into(), self.weight_decay.to_string()),
+            ("epochs".into(), self.epochs.to_string()),
+            (
+                "batch_size".into(),
+                self.batch_size
+                    .map_or("full".to_string(), |batch_size| batch_size.to_string()),
+            ),
+        ])
+    }
+}
+
+/// A stack of fully connected layers
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Network {
+    pub layers: Vec<Layer>,
+}
+
+impl Network {
+    /// Creates a network with the given layer sizes, from the inputs to the outputs
+    pub fn new(sizes: &[usize], hidden: Activation, output: Activation, rng: &mut StdRng) -> Self {
+        let layers = sizes
+            .windows(2)
+            .enumerate()
+            .map(|(position, pair)| {
+                let activation = if position + 2 == sizes.len() { output } else { hidden };
+                Layer::new(pair[0], pair[1], activation, rng)
+            })
+            .collect();
+        Self { layers }
+    }
+
+    pub fn forward(&self, inputs: &[f64]) -> Vec<f64> {
+        self.layers
+            .iter()
+            .fold(inputs.to_vec(), |values, layer| layer.forward(&values))
+    }
+
+    /// Trains the network on the rows towards the targets, logging the mean loss of every epoch
+    /// under the given name
+    pub fn fit(
+        &mut self,
+        rows: &[Vec<f64>],
+        targets: &[Vec<f64>],
+        loss: Loss,
+        settings: &GradientDescent,
+        rng: &mut StdRng,
+        name: &str,
+    ) {
+        let mut weight_velocities = self
+            .layers
+            .iter()
+            .map(|layer| vec![vec![0.0; layer.weights[0].len()]; layer.weights.len()])
+            .collect::<Vec<Vec<Vec<f64>>>>();
+        let mut bias_velocities = self
+            .layers
+            .iter()
+            .map(|layer| vec![0.0; layer.biases.len()])
+            .collect::<Vec<Vec<f64>>>();
+
+        let mut indexes = (0..rows.len()).collect::<Vec<usize>>();
+        let batch_size = settings.batch_size.unwrap_or(rows.len());
+        for epoch in 0..settings.epochs {
+            if batch_size < rows.len() {
+                indexes.shuffle(rng);
+            }
+
+            let mut total_loss = 0.0;
+            for batch in indexes.chunks(batch_size) {
+                let mut weight_gradients = weight_velocities
+                    .iter()
+                    .map(|layer| vec![vec![0.0; layer[0].len()]; layer.len()])
+                    .collect::<Vec<Vec<Vec<f64>>>>();
+                let mut bias_gradients = bias_velocities
+                    .iter()
+                    .map(|layer| vec![0.0; layer.len()])
+                    .collect::<Vec<Vec<f64>>>();
+                for &idx in batch.iter() {
+                    total_loss += self.backpropagate(
+                        &rows[idx],
+                        &targets[idx],
+                        loss,
+                        &mut weight_gradients,
+                        &mut bias_gradients,
+                    );
+                }
+
+                // Momentum update of the weights, weight decay leaves the biases alone
+                let scale = 1.0 / batch.len() as f64;
+                for (position, layer) in self.layers.iter_mut().enumerate() {
+                    for ((weights, velocities), gradients) in layer
+                        .weights
+                        .iter_mut()
+                        .zip(weight_velocities[position].iter_mut())
+                        .zip(weight_gradients[position].iter())
+                    {
+                        for ((weight, velocity), gradient) in
+                            weights.iter_mut().zip(velocities.iter_mut()).zip(gradients.iter())
+                        {
+                            *velocity = settings.momentum * *velocity
+                                - settings.learning_rate
+                                    * (gradient * scale + settings.weight_decay * *weight);
+                            *weight += *velocity;
+                        }
+                    }
+                    for ((bias, velocity), gradient) in layer
+                        .biases
+                        .iter_mut()
+                        .zip(bias_velocities[position].iter_mut())
+                        .zip(bias_gradients[position].iter())
+                    {
+                        *velocity =
+                            settings.momentum * *velocity - settings.learning_rate * gradient * scale;
+                        *bias += *velocity;
+                    }
+                }
+            }
+            println!("Epoch {}: {}: {}", epoch, name, total_loss / rows.len() as f64);
+        }
+    }
+
+    // Adds the gradients of the loss on one training value to the accumulators and returns the
+    // loss
+    fn backpropagate(
+        &self,
+        inputs: &[f64],
+        targets: &[f64],
+        loss: Loss,
+        weight_gradients: &mut [Vec<Vec<f64>>],
+        bias_gradients: &mut [Vec<f64>],
+    ) -> f64 {
+        // Outputs of every layer, starting with the inputs
+        let mut activations = vec![inputs.to_vec()];
+        for layer in self.layers.iter() {
+            let outputs = layer.forward(activations.last().unwrap());
+            activations.push(outputs);
+        }
+
+        let outputs = activations.last().unwrap();
+        let value = match loss {
+            Loss::SquaredError => outputs
+                .iter()
+                .zip(targets.iter())
+                .map(|(output, target)| (output - target).powi(2))
+                .sum::<f64>(),
+            Loss::CrossEntropy => outputs
+                .iter()
+                .zip(targets.iter())
+                .map(|(output, target)| -target * output.max(MIN_PROBABILITY).ln())
+                .sum::<f64>(),
+        };
+
+        // Softmax with cross entropy and a linear output with squared error both have the
+        // difference to the target as error signal
+        let output_activation = self.layers.last().unwrap().activation;
+        let mut deltas = outputs
+            .iter()
+            .zip(targets.iter())
+            .map(|(output, target)| {
+                let derivative = match loss {
+                    Loss::CrossEntropy => 1.0,
+                    Loss::SquaredError => output_activation.derivative(*output),
+                };
+                (output - target) * derivative
+            })
+            .collect::<Vec<f64>>();
+
+        for (position, layer) in self.layers.iter().enumerate().rev() {
+            let layer_inputs = &activations[position];
+            for ((gradients, bias_gradient), delta) in weight_gradients[position]
+                .iter_mut()
+                .zip(bias_gradients[position].iter_mut())
+                .zip(deltas.iter())
+            {
+                *bias_gradient += delta;
+                for (gradient, input) in gradients.iter_mut().zip(layer_inputs.iter()) {
+                    *gradient += delta * input;
+                }
+            }
+
+            if position > 0 {
+                let activation = self.layers[position - 1].activation;
+                deltas = layer_inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(input, output)| {
+                        let error = layer
+                            .weights
+                            .iter()
+                            .zip(deltas.iter())
+                            .map(|(weights, delta)| weights[input] * delta)
+                            .sum::<f64>();
+                        error * activation.derivative(*output)
+                    })
+                    .collect();
+            }
+        }
+
+        value
+    }
+}
+
+/// Parses hidden layer sizes given as a comma separated list, "none" for no hidden layer
+pub fn parse_layer_sizes(sizes: &str) -> Result<Vec<usize>, Box<dyn Error>> {
+    if sizes == "none" || sizes.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    sizes
+        .split(',')
+        .map(|size| match size.trim().parse::<usize>() {
+            Ok(0) => Err("A layer needs at least one unit!".into()),
+            Ok(size) => Ok(size),
+            Err(e) => Err(e.into()),
+        })
+        .collect()
+}
+
+/// Formats hidden layer sizes as a comma separated list, "none" for no hidden layer
+pub fn format_layer_sizes(sizes: &[usize]) -> String {
+    if sizes.is_empty() {
+        return "none".to_string();
+    }
+    sizes
+        .iter()
+        .map(|size| size.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultilayerPerceptron {
+    pub label_index: usize,
+    pub hidden_layers: Vec<usize>,
+    pub activation: Activation,
+    pub settings: GradientDescent,
+    // Epochs of autoencoder pretraining of the hidden layers, no pretraining when zero
+    pub pretraining_epochs: usize,
+    pub seed: u64,
+    classification: bool,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    // Standardization of the regression target
+    target_mean: f64,
+    target_scale: f64,
+    network: Network,
+}
+
+impl Model for MultilayerPerceptron {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        if !self.classification {
+            return self.predict(sample);
+        }
+
+        // Return the class with the largest probability, ties go to the smallest class
+        let outputs = self.network.forward(&self.features(sample));
+        let best = outputs
+            .iter()
+            .enumerate()
+            .fold(0, |best, (position, &output)| {
+                if output > outputs[best] {
+                    position
+                } else {
+                    best
+                }
+            });
+        self.classes[best]
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        if self.classification {
+            return self.label(sample);
+        }
+        self.network.forward(&self.features(sample))[0] * self.target_scale + self.target_mean
+    }
+
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        if !self.classification {
+            return Vec::new();
+        }
+        self.classes
+            .iter()
+            .copied()
+            .zip(self.network.forward(&self.features(sample)))
+            .collect()
+    }
+
+    fn type_id(&self) -> &'static str {
+        "MultilayerPerceptron"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        let mut ret = self.settings.get_hyperparameters();
+        ret.insert("hidden_layers".into(), format_layer_sizes(&self.hidden_layers));
+        ret.insert("activation".into(), self.activation.to_string());
+        ret.insert("pretraining_epochs".into(), self.pretraining_epochs.to_string());
+        ret.insert("seed".into(), self.seed.to_string());
+        ret
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            if self.settings.set_hyperparameter(key, val)? {
+                continue;
+            }
+            match key.as_str() {
+                "hidden_layers" => {
+                    self.hidden_layers = parse_layer_sizes(val)?;
+                }
+                "activation" => {
+                    self.activation = Activation::from_name(val)?;
+                }
+                "pretraining_epochs" => {
+                    self.pretraining_epochs = val.parse::<usize>()?;
+                }
+                "seed" => {
+                    self.seed = val.parse::<u64>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl MultilayerPerceptron {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            hidden_layers: vec![16],
+            activation: Activation::Relu,
+            settings: GradientDescent::default(),
+            pretraining_epochs: 0,
+            seed: 0,
+            classification: true,
+            classes: Vec::new(),
+            target_mean: 0.0,
+            target_scale: 1.0,
+            network: Network::default(),
+        }
+    }
+
+    /// Trains the network on the training values. Must be called after the hyperparameters are
+    /// set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.classification = match context.task.as_deref() {
+            Some("classification") | None => true,
+            Some("regression") => false,
+            Some(_) => {
+                return Err(
+                    "Invalid model task, only regression and classification are supported".into(),
+                )
+            }
+        };
+
+        let rows = training_values
+            .iter()
+            .map(|sample| self.features(sample))
+            .collect::<Vec<Vec<Numeric>>>();
+        let labels = training_values
+            .iter()
+            .map(|sample| sample[self.label_index])
+            .collect::<Vec<Numeric>>();
+
+        let (targets, output_activation, loss) = if self.classification {
+            self.classes = context
+                .classes(self.label_index)
+                .unwrap_or_else(|| training_classes(training_values, self.label_index));
+            let targets = labels
+                .iter()
+                .map(|label| {
+                    self.classes
+                        .iter()
+                        .map(|class| {
+                            if (label - class).abs() < NUMERIC_DIGIT_PRECISION {
+                                1.0
+                            } else {
+                                0.0
+                            }
+                        })
+                        .collect()
+                })
+                .collect::<Vec<Vec<f64>>>();
+            (targets, Activation::Softmax, Loss::CrossEntropy)
+        } else {
+            let num_samples = labels.len() as f64;
+            self.target_mean = labels.iter().sum::<f64>() / num_samples;
+            let variance = labels
+                .iter()
+                .map(|label| (label - self.target_mean).powi(2))
+                .sum::<f64>()
+                / num_samples;
+            self.target_scale = if variance > 0.0 { variance.sqrt() } else { 1.0 };
+            let targets = labels
+                .iter()
+                .map(|label| vec![(label - self.target_mean) / self.target_scale])
+                .collect::<Vec<Vec<f64>>>();
+            (targets, Activation::Linear, Loss::SquaredError)
+        };
+
+        let mut sizes = vec![rows[0].len()];
+        sizes.extend(self.hidden_layers.iter());
+        sizes.push(targets[0].len());
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        self.network = Network::new(&sizes, self.activation, output_activation, &mut rng);
+        if self.pretraining_epochs > 0 && !self.hidden_layers.is_empty() {
+            self.pretrain(&rows)?;
+        }
+        self.network
+            .fit(&rows, &targets, loss, &self.settings, &mut rng, "loss");
+
+        Ok(())
+    }
+
+    // Trains an autoencoder whose encoder has the shape of the hidden layers and copies the
+    // encoder weights into the hidden layers
+    fn pretrain(&mut self, rows: &[Vec<Numeric>]) -> Result<(), Box<dyn Error>> {
+        let num_hidden = self.hidden_layers.len();
+        let mut autoencoder = Autoencoder::new();
+        autoencoder.hidden_layers = self.hidden_layers[..num_hidden - 1].to_vec();
+        autoencoder.encoding_size = self.hidden_layers[num_hidden - 1];
+        autoencoder.activation = self.activation;
+        autoencoder.settings = GradientDescent {
+            epochs: self.pretraining_epochs,
+            ..self.settings.clone()
+        };
+        autoencoder.seed = self.seed;
+        autoencoder.fit(rows)?;
+        println!(
+            "Pretrained hidden layers, reconstruction error: {}",
+            autoencoder.reconstruction_error(rows)
+        );
+
+        self.network
+            .layers
+            .splice(..num_hidden, autoencoder.encoder().iter().cloned());
+        Ok(())
+    }
+
+    // Returns the feature values of a sample, skipping the label
+    fn features(&self, sample: &[Numeric]) -> Vec<Numeric> {
+        sample
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| column != self.label_index)
+            .map(|(_, value)| *value)
+            .collect()
+    }
+}
+
+pub struct MultilayerPerceptronTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for MultilayerPerceptronTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = MultilayerPerceptron::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built multilayer perceptron with layers {:?}",
+            model
+                .network
+                .layers
+                .iter()
+                .map(|layer| (layer.biases.len(), layer.activation.to_string()))
+                .collect::<Vec<(usize, String)>>()
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -1,12 +1,36 @@
 //! This module contains the implemented ML models
 
+mod adaboost;
+pub mod autoencoder;
+mod decision_tree;
+mod distance;
+mod ensemble;
+mod gradient_boosting;
+pub mod kmeans;
 mod knn;
 mod knn_condensed;
 mod knn_edited;
+mod knn_radius;
 mod knn_simple;
+mod linalg;
+mod linear_regression;
+mod logistic_regression;
+mod lvq;
+pub mod mlp;
+mod naive_bayes;
 mod null;
+mod random_forest;
+mod rbf_network;
+mod regression_tree;
+mod spatial_index;
+mod svm;
 
-use crate::types::Numeric;
+use crate::config::ValidationConfigs;
+use crate::data::column::ColumnInfo;
+use crate::data::data_frame::DataFrame;
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 
 use std::collections::HashMap;
 use std::error::Error;
@@ -14,20 +38,181 @@ use std::error::Error;
 pub trait Model {
     fn predict(&self, sample: &[Numeric]) -> Numeric;
     fn label(&self, sample: &[Numeric]) -> Numeric;
+    // Models without a measure of confidence give all the probability to the predicted label
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        vec![(self.label(sample), 1.0)]
+    }
     fn type_id(&self) -> &'static str;
+    // Fitted state of the model, read back by `SavedModel::load` according to the type id
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>>;
     fn get_hyperparameters(&self) -> HashMap<String, String>;
     fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>>;
 }
 
+/// A fitted model as it is written to a file: its type and its fitted state. Models made of other
+/// models keep their states as YAML values, which can't hold nested YAML tags, so model enums
+/// carrying data are tagged with a `kind` field instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedModel {
+    pub type_id: String,
+    pub state: serde_yaml::Value,
+}
+
+impl SavedModel {
+    pub fn new(model: &dyn Model) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            type_id: model.type_id().to_string(),
+            state: model.save_state()?,
+        })
+    }
+
+    /// Rebuilds the model from its state, the type id tells which model the state belongs to
+    pub fn load(&self) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        let state = self.state.clone();
+        match self.type_id.as_str() {
+            "NullModel" => load_state::<null::NullModel>(state),
+            "DecisionTree" => load_state::<decision_tree::DecisionTree>(state),
+            "RegressionTree" => load_state::<regression_tree::RegressionTree>(state),
+            "RandomForest" => load_state::<random_forest::RandomForest>(state),
+            "GradientBoosting" => load_state::<gradient_boosting::GradientBoosting>(state),
+            "LinearRegression" => load_state::<linear_regression::LinearRegression>(state),
+            "LogisticRegression" => load_state::<logistic_regression::LogisticRegression>(state),
+            "MultilayerPerceptron" => load_state::<mlp::MultilayerPerceptron>(state),
+            "NaiveBayes" => load_state::<naive_bayes::NaiveBayes>(state),
+            "SupportVectorMachine" => load_state::<svm::SupportVectorMachine>(state),
+            "LearningVectorQuantization" => load_state::<lvq::LearningVectorQuantization>(state),
+            "RbfNetwork" => load_state::<rbf_network::RbfNetwork>(state),
+            "AdaBoost" => load_state::<adaboost::AdaBoost>(state),
+            "Ensemble" => load_state::<ensemble::Ensemble>(state),
+            "KNearestNeighbor" => load_state::<knn::KNearestNeighbor>(state),
+            "RadiusNearestNeighbor" => load_state::<knn_radius::RadiusNearestNeighbor>(state),
+            _ => Err(format!("Unsupported saved model type: {}", self.type_id).into()),
+        }
+    }
+}
+
+fn load_state<M>(state: serde_yaml::Value) -> Result<Box<dyn Model>, Box<dyn Error>>
+where
+    M: Model + de::DeserializeOwned + 'static,
+{
+    Ok(Box::new(serde_yaml::from_value::<M>(state)?))
+}
+
+// Models made of other models (forests, ensembles, boosting) save every member with its type
+impl Serialize for Box<dyn Model> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SavedModel::new(self.as_ref())
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Box<dyn Model> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        SavedModel::deserialize(deserializer)?
+            .load()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Information about the data set that model builders can use besides the training values
+#[derive(Debug, Clone, Default)]
+pub struct TrainingContext {
+    pub columns: Vec<ColumnInfo>,
+    // Partitioning used by the experiment, for models that cross-validate their own settings
+    pub partitioning: Option<ValidationConfigs>,
+    // Task of the experiment, for models that learn differently for classification and regression
+    pub task: Option<String>,
+}
+
+impl TrainingContext {
+    pub fn new(df: &DataFrame<Numeric>) -> Self {
+        Self {
+            columns: df.columns_info(),
+            partitioning: None,
+            task: None,
+        }
+    }
+
+    pub fn with_partitioning(mut self, partitioning: &ValidationConfigs) -> Self {
+        self.partitioning = Some(partitioning.clone());
+        self
+    }
+
+    pub fn with_task(mut self, task: &str) -> Self {
+        self.task = Some(task.to_string());
+        self
+    }
+
+    /// Returns the context of a table reduced to the given columns, in the given order
+    pub fn select_columns(&self, columns: &[usize]) -> Self {
+        Self {
+            columns: columns
+                .iter()
+                .map(|&column| self.columns.get(column).cloned().unwrap_or_default())
+                .collect(),
+            partitioning: self.partitioning.clone(),
+            task: self.task.clone(),
+        }
+    }
+
+    /// Returns the header of a column, columns without headers are named by their index
+    pub fn column_name(&self, column: usize) -> String {
+        self.columns
+            .get(column)
+            .and_then(|info| info.name.clone())
+            .unwrap_or_else(|| format!("x[{}]", column))
+    }
+
+    // Returns which columns hold categorical values, columns without information are numeric
+    pub fn categorical_columns(&self) -> Vec<bool> {
+        self.columns.iter().map(|column| column.is_categorical()).collect()
+    }
+
+    /// Returns the class codes recorded in the metadata of the label column, sorted by value
+    pub fn classes(&self, label_index: usize) -> Option<Vec<Numeric>> {
+        let metadata = self.columns.get(label_index)?.metadata.as_ref()?;
+        let mut classes = metadata
+            .keys()
+            .map(|&code| Numeric::from(code))
+            .collect::<Vec<Numeric>>();
+        classes.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        Some(classes)
+    }
+}
+
+/// Returns the classes present in the label column of the training values, sorted by value
+pub fn training_classes(training_values: &[Box<[Numeric]>], label_index: usize) -> Vec<Numeric> {
+    let mut classes = training_values
+        .iter()
+        .map(|sample| (sample[label_index] / NUMERIC_DIGIT_PRECISION) as i64)
+        .collect::<Vec<i64>>();
+    classes.sort();
+    classes.dedup();
+    classes
+        .into_iter()
+        .map(|class| (class as f64) * NUMERIC_DIGIT_PRECISION)
+        .collect()
+}
+
 pub trait ModelBuilder {
     fn new() -> Self
     where
         Self: Sized;
     fn with_hyperparameters(&mut self, features: &HashMap<String, String>) -> Result<(), Box<dyn Error>>;
+    fn with_context(&mut self, _context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+    // Builders whose models can learn from weighted training values, the other builders ignore
+    // the sample weights
+    fn supports_sample_weights(&self) -> bool {
+        false
+    }
     fn build(
         &mut self,
         training_values: &[Box<[Numeric]>],
         target_value_idx: usize,
+        sample_weights: Option<&[Numeric]>,
     ) -> Result<Box<dyn Model>, Box<dyn Error>>;
 }
 
@@ -35,11 +220,33 @@ pub fn get_model_builder(model_name: &str) -> Result<Box<dyn ModelBuilder>, Box<
     match model_name {
         "null-regression" => Ok(Box::new(null::NullRegressionModelTrainer::new())),
         "null-classifier" => Ok(Box::new(null::NullClassificationModelTrainer::new())),
+        "decision-tree" => Ok(Box::new(decision_tree::DecisionTreeTrainer::new())),
+        "regression-tree" => Ok(Box::new(regression_tree::RegressionTreeTrainer::new())),
+        "random-forest" => Ok(Box::new(random_forest::RandomForestTrainer::new())),
+        "gradient-boosting" => Ok(Box::new(gradient_boosting::GradientBoostingTrainer::new())),
+        "linear-regression" => Ok(Box::new(linear_regression::LinearRegressionTrainer::new())),
+        "ridge-regression" => Ok(Box::new(linear_regression::LinearRegressionTrainer::ridge())),
+        "lasso-regression" => Ok(Box::new(linear_regression::LinearRegressionTrainer::lasso())),
+        "logistic-regression" => Ok(Box::new(logistic_regression::LogisticRegressionTrainer::new())),
+        "softmax-regression" => Ok(Box::new(logistic_regression::LogisticRegressionTrainer::softmax())),
+        "mlp" => Ok(Box::new(mlp::MultilayerPerceptronTrainer::new())),
+        "naive-bayes" => Ok(Box::new(naive_bayes::NaiveBayesTrainer::new())),
+        "gaussian-naive-bayes" => Ok(Box::new(naive_bayes::NaiveBayesTrainer::gaussian())),
+        "categorical-naive-bayes" => Ok(Box::new(naive_bayes::NaiveBayesTrainer::categorical())),
+        "svm" => Ok(Box::new(svm::SupportVectorMachineTrainer::new())),
+        "lvq1" => Ok(Box::new(lvq::LearningVectorQuantizationTrainer::new())),
+        "lvq2.1" => Ok(Box::new(lvq::LearningVectorQuantizationTrainer::lvq21())),
+        "rbf-network" => Ok(Box::new(rbf_network::RbfNetworkTrainer::new())),
+        "adaboost" => Ok(Box::new(adaboost::AdaBoostTrainer::new())),
+        "voting" => Ok(Box::new(ensemble::EnsembleTrainer::new())),
+        "bagging" => Ok(Box::new(ensemble::EnsembleTrainer::bagging())),
+        "stacking" => Ok(Box::new(ensemble::EnsembleTrainer::stacking())),
         "knn-simple" => Ok(Box::new(knn_simple::SimpleKNearestNeighborTrainer::new())),
         "knn-condensed" => Ok(Box::new(
             knn_condensed::CondensedKNearestNeighborTrainer::new(),
         )),
         "knn-edited" => Ok(Box::new(knn_edited::EditedKNearestNeighborTrainer::new())),
+        "knn-radius" => Ok(Box::new(knn_radius::RadiusNearestNeighborTrainer::new())),
         _ => Err("Unsupported model: {model_name}".into()),
     }
 }
@@ -0,0 +1,408 @@
+// naive_bayes.rs
+
+//! This file implements naive Bayes classifiers, which assume the features are independent given
+//! the class. The probability of a class is its frequency in the training values times the
+//! likelihood of every feature value under that class. Numeric features are modeled with a normal
+//! distribution per class, categorical features with the frequency of each category per class,
+//! smoothed by adding `alpha` to every count (Laplace smoothing). The Gaussian variant models
+//! every feature as numeric, the categorical variant models every feature as categorical (e.g.
+//! after discretization) and the mixed variant follows the column metadata: ordinal and nominal
+//! columns are categorical, the others numeric.
+
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::f64::consts::PI;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Likelihood {
+    Gaussian,
+    Categorical,
+    Mixed,
+}
+
+impl fmt::Display for Likelihood {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Gaussian => "gaussian",
+            Self::Categorical => "categorical",
+            Self::Mixed => "mixed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Distribution of a feature within one class
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+enum FeatureDistribution {
+    Gaussian {
+        mean: f64,
+        variance: f64,
+    },
+    Categorical {
+        counts: HashMap<i64, usize>,
+        num_categories: usize,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClassDistribution {
+    class: Numeric,
+    count: usize,
+    // Distribution of each feature column, in column order skipping the label
+    features: Vec<FeatureDistribution>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NaiveBayes {
+    pub label_index: usize,
+    pub likelihood: Likelihood,
+    pub alpha: f64,
+    // Share of the largest feature variance added to every variance, for numerical stability
+    pub var_smoothing: f64,
+    num_samples: usize,
+    classes: Vec<ClassDistribution>,
+}
+
+impl Model for NaiveBayes {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        // Return the class with the largest posterior, ties go to the smallest class
+        let posteriors = self.log_posteriors(sample);
+        let best = posteriors
+            .iter()
+            .enumerate()
+            .fold(0, |best, (position, &posterior)| {
+                if posterior > posteriors[best] {
+                    position
+                } else {
+                    best
+                }
+            });
+        self.classes[best].class
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        self.label(sample)
+    }
+
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        // Normalize the posteriors, subtracting the largest so the exponentials can't underflow
+        let posteriors = self.log_posteriors(sample);
+        let max_posterior = posteriors.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let exponentials = posteriors
+            .iter()
+            .map(|posterior| (posterior - max_posterior).exp())
+            .collect::<Vec<f64>>();
+        let total = exponentials.iter().sum::<f64>();
+        self.classes
+            .iter()
+            .zip(exponentials.iter())
+            .map(|(distribution, value)| (distribution.class, value / total))
+            .collect()
+    }
+
+    fn type_id(&self) -> &'static str {
+        "NaiveBayes"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("likelihood".into(), self.likelihood.to_string()),
+            ("alpha".into(), self.alpha.to_string()),
+            ("var_smoothing".into(), self.var_smoothing.to_string()),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "alpha" => {
+                    let alpha = val.parse::<f64>()?;
+                    if alpha <= 0.0 {
+                        return Err("The smoothing parameter must be positive!".into());
+                    }
+                    self.alpha = alpha;
+                }
+                "var_smoothing" => {
+                    self.var_smoothing = val.parse::<f64>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl NaiveBayes {
+    pub fn new(label_index: usize, likelihood: Likelihood) -> Self {
+        Self {
+            label_index,
+            likelihood,
+            alpha: 1.0,
+            var_smoothing: 1e-9,
+            num_samples: 0,
+            classes: Vec::new(),
+        }
+    }
+
+    /// Estimates the class priors and the feature distributions of every class. Must be called
+    /// after the hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        let num_columns = training_values[0].len();
+        let feature_columns = (0..num_columns)
+            .filter(|&column| column != self.label_index)
+            .collect::<Vec<usize>>();
+
+        // Decide how each feature is modeled
+        let categorical = match self.likelihood {
+            Likelihood::Gaussian => vec![false; num_columns],
+            Likelihood::Categorical => vec![true; num_columns],
+            Likelihood::Mixed => {
+                let mut categorical = context.categorical_columns();
+                categorical.resize(num_columns, false);
+                categorical
+            }
+        };
+
+        // Categories come from the metadata when there is one, otherwise from the training values
+        let num_categories = feature_columns
+            .iter()
+            .map(|&column| {
+                let seen = training_values
+                    .iter()
+                    .map(|sample| value_key(sample[column]))
+                    .collect::<HashSet<i64>>()
+                    .len();
+                let known = context
+                    .columns
+                    .get(column)
+                    .and_then(|info| info.metadata.as_ref())
+                    .map_or(0, |metadata| metadata.len());
+                seen.max(known)
+            })
+            .collect::<Vec<usize>>();
+
+        // Variances are smoothed relative to the largest variance over all the training values
+        let max_variance = feature_columns
+            .iter()
+            .map(|&column| {
+                let values = training_values
+                    .iter()
+                    .map(|sample| sample[column])
+                    .collect::<Vec<Numeric>>();
+                mean_variance(&values).1
+            })
+            .fold(0.0, f64::max);
+        let epsilon = (self.var_smoothing * max_variance).max(f64::MIN_POSITIVE);
+
+        let classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+        self.num_samples = training_values.len();
+        self.classes = classes
+            .iter()
+            .map(|&class| {
+                let members = training_values
+                    .iter()
+                    .filter(|sample| (sample[self.label_index] - class).abs() < NUMERIC_DIGIT_PRECISION)
+                    .collect::<Vec<&Box<[Numeric]>>>();
+                let features = feature_columns
+                    .iter()
+                    .zip(num_categories.iter())
+                    .map(|(&column, &num_categories)| {
+                        if categorical[column] {
+                            let mut counts = HashMap::new();
+                            for sample in members.iter() {
+                                *counts.entry(value_key(sample[column])).or_insert(0) += 1;
+                            }
+                            FeatureDistribution::Categorical {
+                                counts,
+                                num_categories,
+                            }
+                        } else {
+                            let values = members
+                                .iter()
+                                .map(|sample| sample[column])
+                                .collect::<Vec<Numeric>>();
+                            let (mean, variance) = mean_variance(&values);
+                            FeatureDistribution::Gaussian {
+                                mean,
+                                variance: variance + epsilon,
+                            }
+                        }
+                    })
+                    .collect();
+                ClassDistribution {
+                    class,
+                    count: members.len(),
+                    features,
+                }
+            })
+            .collect();
+
+        Ok(())
+    }
+
+    // Returns the log of the prior times the likelihood of the sample for every class
+    fn log_posteriors(&self, sample: &[Numeric]) -> Vec<f64> {
+        let values = sample
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| column != self.label_index)
+            .map(|(_, value)| *value)
+            .collect::<Vec<Numeric>>();
+
+        self.classes
+            .iter()
+            .map(|distribution| {
+                // Classes missing from the training values can't be predicted
+                if distribution.count == 0 {
+                    return f64::NEG_INFINITY;
+                }
+                let prior = (distribution.count as f64 / self.num_samples as f64).ln();
+                distribution
+                    .features
+                    .iter()
+                    .zip(values.iter())
+                    .fold(prior, |acc, (feature, &value)| {
+                        acc + self.log_likelihood(feature, distribution.count, value)
+                    })
+            })
+            .collect()
+    }
+
+    fn log_likelihood(&self, feature: &FeatureDistribution, class_count: usize, value: Numeric) -> f64 {
+        match feature {
+            FeatureDistribution::Gaussian { mean, variance } => {
+                -0.5 * ((2.0 * PI * variance).ln() + (value - mean).powi(2) / variance)
+            }
+            FeatureDistribution::Categorical {
+                counts,
+                num_categories,
+            } => {
+                let count = counts.get(&value_key(value)).copied().unwrap_or(0);
+                ((count as f64 + self.alpha)
+                    / (class_count as f64 + self.alpha * *num_categories as f64))
+                    .ln()
+            }
+        }
+    }
+
+    /// Returns the number of features modeled as normal and as categorical
+    pub fn feature_kinds(&self) -> (usize, usize) {
+        self.classes.first().map_or((0, 0), |distribution| {
+            let categorical = distribution
+                .features
+                .iter()
+                .filter(|feature| matches!(feature, FeatureDistribution::Categorical { .. }))
+                .count();
+            (distribution.features.len() - categorical, categorical)
+        })
+    }
+}
+
+fn value_key(value: Numeric) -> i64 {
+    (value / NUMERIC_DIGIT_PRECISION) as i64
+}
+
+// Returns the mean and the population variance of the values
+fn mean_variance(values: &[Numeric]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f64>() / count;
+    (mean, variance)
+}
+
+pub struct NaiveBayesTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+    likelihood: Likelihood,
+}
+
+impl NaiveBayesTrainer {
+    pub fn gaussian() -> Self {
+        Self {
+            likelihood: Likelihood::Gaussian,
+            ..Self::new()
+        }
+    }
+
+    pub fn categorical() -> Self {
+        Self {
+            likelihood: Likelihood::Categorical,
+            ..Self::new()
+        }
+    }
+}
+
+impl ModelBuilder for NaiveBayesTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+            likelihood: Likelihood::Mixed,
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = NaiveBayes::new(target_value_idx, self.likelihood);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        let (gaussian, categorical) = model.feature_kinds();
+        println!(
+            "Built {} naive Bayes model with {} gaussian and {} categorical features",
+            model.likelihood, gaussian, categorical
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -5,14 +5,20 @@
 
 use super::Model;
 use super::ModelBuilder;
+use super::{training_classes, TrainingContext};
 
-use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
 use std::error::Error;
 
+#[derive(Serialize, Deserialize)]
 pub struct NullModel {
     return_value: Numeric,
+    // Frequency of each class in the training data, empty for regression
+    probabilities: ClassProbabilities,
 }
 
 impl Model for NullModel {
@@ -23,10 +29,21 @@ impl Model for NullModel {
     fn label(&self, _sample: &[Numeric]) -> Numeric {
         self.return_value
     }
+
+    fn predict_proba(&self, _sample: &[Numeric]) -> ClassProbabilities {
+        if self.probabilities.is_empty() {
+            return vec![(self.return_value, 1.0)];
+        }
+        self.probabilities.clone()
+    }
     fn type_id(&self) -> &'static str {
         "NullModel"
     }
 
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
     fn get_hyperparameters(&self) -> HashMap<String, String> {
         HashMap::from([("return_value".into(), self.return_value.to_string())])
     }
@@ -56,6 +73,7 @@ impl ModelBuilder for NullRegressionModelTrainer {
         &mut self,
         training_values: &[Box<[Numeric]>],
         target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
     ) -> Result<Box<dyn Model>, Box<dyn Error>> {
         // Calculate mean of labels
         let mean = training_values
@@ -63,28 +81,41 @@ impl ModelBuilder for NullRegressionModelTrainer {
             .fold(0.0, |acc, val| acc + val[target_value_idx])
             / (training_values.len() as f64);
 
-        Ok(Box::new(NullModel { return_value: mean }))
+        Ok(Box::new(NullModel {
+            return_value: mean,
+            probabilities: Vec::new(),
+        }))
     }
 }
 
-pub struct NullClassificationModelTrainer;
+pub struct NullClassificationModelTrainer {
+    context: TrainingContext,
+}
 
 impl ModelBuilder for NullClassificationModelTrainer {
     fn new() -> Self
     where
         Self: Sized,
     {
-        Self {}
+        Self {
+            context: TrainingContext::default(),
+        }
     }
 
     fn with_hyperparameters(&mut self, _features: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
         Ok(())
     }
 
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
     fn build(
         &mut self,
         training_values: &[Box<[Numeric]>],
         target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
     ) -> Result<Box<dyn Model>, Box<dyn Error>> {
         // Build a map of counters for the most common value
         let mut value_count = HashMap::new();
@@ -103,8 +134,22 @@ impl ModelBuilder for NullClassificationModelTrainer {
             .map(|(val, _)| val)
             .ok_or("No mode found!")?;
 
+        // The probability of each class is its frequency in the training data
+        let probabilities = self
+            .context
+            .classes(target_value_idx)
+            .unwrap_or_else(|| training_classes(training_values, target_value_idx))
+            .into_iter()
+            .map(|class| {
+                let key = (class / NUMERIC_DIGIT_PRECISION) as i64;
+                let count = value_count.get(&key).copied().unwrap_or(0);
+                (class, count as f64 / training_values.len() as f64)
+            })
+            .collect();
+
         Ok(Box::new(NullModel {
             return_value: (*mode as f64) * NUMERIC_DIGIT_PRECISION,
+            probabilities,
         }))
     }
 }
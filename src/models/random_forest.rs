@@ -0,0 +1,358 @@
+// random_forest.rs
+
+//! This file implements a random forest. Every tree is grown on a bootstrap sample of the training
+//! values and only considers a random subset of the features at each split. Classification
+//! forests grow decision trees and predict by majority vote, regression forests grow regression
+//! trees and predict the mean of the trees. The training values a tree didn't see (out-of-bag)
+//! are used to estimate the error of the forest without a separate validation set.
+
+use super::decision_tree::DecisionTree;
+use super::regression_tree::RegressionTree;
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{ClassProbabilities, Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum MaxFeatures {
+    Sqrt,
+    Log2,
+    All,
+    Count(usize),
+}
+
+impl MaxFeatures {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "sqrt" => Ok(Self::Sqrt),
+            "log2" => Ok(Self::Log2),
+            "all" => Ok(Self::All),
+            _ => Ok(Self::Count(name.parse::<usize>()?)),
+        }
+    }
+
+    /// Returns the number of features to consider at each split, at least one
+    pub fn resolve(&self, num_features: usize) -> usize {
+        let count = match self {
+            Self::Sqrt => (num_features as f64).sqrt().round() as usize,
+            Self::Log2 => (num_features as f64).log2().round() as usize,
+            Self::All => num_features,
+            Self::Count(count) => *count,
+        };
+        count.clamp(1, num_features.max(1))
+    }
+}
+
+impl fmt::Display for MaxFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sqrt => write!(f, "sqrt"),
+            Self::Log2 => write!(f, "log2"),
+            Self::All => write!(f, "all"),
+            Self::Count(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RandomForest {
+    pub label_index: usize,
+    pub num_trees: usize,
+    pub max_features: MaxFeatures,
+    pub max_depth: Option<usize>,
+    classification: bool,
+    // Class codes of the label column, sorted by value
+    classes: Vec<Numeric>,
+    trees: Vec<Box<dyn Model>>,
+    oob_error: Option<f64>,
+}
+
+impl Model for RandomForest {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        let labels = self
+            .trees
+            .iter()
+            .map(|tree| tree.label(sample))
+            .collect::<Vec<Numeric>>();
+        self.aggregate(&labels)
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        let predictions = self
+            .trees
+            .iter()
+            .map(|tree| tree.predict(sample))
+            .collect::<Vec<Numeric>>();
+        self.aggregate(&predictions)
+    }
+
+    // Returns the share of the trees voting for each class
+    fn predict_proba(&self, sample: &[Numeric]) -> ClassProbabilities {
+        let labels = self
+            .trees
+            .iter()
+            .map(|tree| tree.label(sample))
+            .collect::<Vec<Numeric>>();
+        self.classes
+            .iter()
+            .map(|&class| {
+                let votes = labels
+                    .iter()
+                    .filter(|label| (*label - class).abs() < NUMERIC_DIGIT_PRECISION)
+                    .count();
+                (class, votes as f64 / self.trees.len() as f64)
+            })
+            .collect()
+    }
+
+    fn type_id(&self) -> &'static str {
+        "RandomForest"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("num_trees".into(), self.num_trees.to_string()),
+            ("max_features".into(), self.max_features.to_string()),
+            (
+                "max_depth".into(),
+                self.max_depth
+                    .map_or("none".to_string(), |max_depth| max_depth.to_string()),
+            ),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "num_trees" => {
+                    self.num_trees = val.parse::<usize>()?;
+                    if self.num_trees == 0 {
+                        return Err("A random forest needs at least one tree!".into());
+                    }
+                }
+                "max_features" => {
+                    self.max_features = MaxFeatures::from_name(val)?;
+                }
+                "max_depth" => {
+                    self.max_depth = match val.as_str() {
+                        "none" => None,
+                        _ => Some(val.parse::<usize>()?),
+                    };
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RandomForest {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            num_trees: 50,
+            max_features: MaxFeatures::Sqrt,
+            max_depth: None,
+            classification: true,
+            classes: Vec::new(),
+            trees: Vec::new(),
+            oob_error: None,
+        }
+    }
+
+    /// Grows the trees on bootstrap samples of the training values and estimates the out-of-bag
+    /// error. Must be called after the hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.classification = match context.task.as_deref() {
+            Some("classification") | None => true,
+            Some("regression") => false,
+            Some(_) => {
+                return Err(
+                    "Invalid model task, only regression and classification are supported".into(),
+                )
+            }
+        };
+        self.classes = context
+            .classes(self.label_index)
+            .unwrap_or_else(|| training_classes(training_values, self.label_index));
+
+        let num_features = training_values[0].len() - 1;
+        let max_features = self.max_features.resolve(num_features);
+
+        // Predictions of the trees for the training values left out of their bootstrap sample
+        let mut oob_predictions = vec![Vec::new(); training_values.len()];
+
+        let mut rng = rand::thread_rng();
+        self.trees.clear();
+        for _ in 0..self.num_trees {
+            // Draw a bootstrap sample of the same size as the training values
+            let mut in_bag = vec![false; training_values.len()];
+            let mut bootstrap = Vec::with_capacity(training_values.len());
+            for _ in 0..training_values.len() {
+                let idx = rng.gen_range(0..training_values.len());
+                in_bag[idx] = true;
+                bootstrap.push(training_values[idx].clone());
+            }
+
+            let tree: Box<dyn Model> = if self.classification {
+                let mut tree = DecisionTree::new(self.label_index);
+                tree.max_depth = self.max_depth;
+                tree.max_features = Some(max_features);
+                tree.fit(&bootstrap, context)?;
+                Box::new(tree)
+            } else {
+                let mut tree = RegressionTree::new(self.label_index);
+                tree.max_depth = self.max_depth;
+                tree.max_features = Some(max_features);
+                tree.fit(&bootstrap, context)?;
+                Box::new(tree)
+            };
+
+            for (idx, sample) in training_values.iter().enumerate() {
+                if !in_bag[idx] {
+                    oob_predictions[idx].push(tree.predict(sample));
+                }
+            }
+            self.trees.push(tree);
+        }
+
+        // Misclassification rate or mean squared error over the training values that were left
+        // out of at least one tree
+        let oob_errors = training_values
+            .iter()
+            .zip(oob_predictions.iter())
+            .filter(|(_, predictions)| !predictions.is_empty())
+            .map(|(sample, predictions)| {
+                let prediction = self.aggregate(predictions);
+                let target = sample[self.label_index];
+                if !self.classification {
+                    (prediction - target).powi(2)
+                } else if (prediction - target).abs() < NUMERIC_DIGIT_PRECISION {
+                    0.0
+                } else {
+                    1.0
+                }
+            })
+            .collect::<Vec<f64>>();
+        self.oob_error = if oob_errors.is_empty() {
+            None
+        } else {
+            Some(oob_errors.iter().sum::<f64>() / oob_errors.len() as f64)
+        };
+
+        Ok(())
+    }
+
+    pub fn oob_error(&self) -> Option<f64> {
+        self.oob_error
+    }
+
+    // Combines the predictions of the trees, by vote for classification or by mean for regression
+    fn aggregate(&self, predictions: &[Numeric]) -> Numeric {
+        if self.classification {
+            majority(predictions)
+        } else {
+            predictions.iter().sum::<Numeric>() / predictions.len() as f64
+        }
+    }
+}
+
+// Returns the most common label, ties go to the smallest label
+fn majority(labels: &[Numeric]) -> Numeric {
+    let mut votes: Vec<(i64, usize)> = Vec::new();
+    for label in labels.iter() {
+        let key = (label / NUMERIC_DIGIT_PRECISION) as i64;
+        match votes.iter_mut().find(|(voted, _)| *voted == key) {
+            Some((_, count)) => *count += 1,
+            None => votes.push((key, 1)),
+        }
+    }
+    votes.sort();
+    let mode = votes
+        .iter()
+        .fold(None, |best: Option<&(i64, usize)>, vote| match best {
+            Some(best) if best.1 >= vote.1 => Some(best),
+            _ => Some(vote),
+        })
+        .map(|(key, _)| *key)
+        .expect("No mode found!");
+    (mode as f64) * NUMERIC_DIGIT_PRECISION
+}
+
+pub struct RandomForestTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for RandomForestTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = RandomForest::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        match model.oob_error() {
+            Some(oob_error) => println!(
+                "Built random forest with {} trees, out-of-bag error: {}",
+                model.num_trees, oob_error
+            ),
+            None => println!(
+                "Built random forest with {} trees, no out-of-bag samples",
+                model.num_trees
+            ),
+        }
+
+        Ok(Box::new(model))
+    }
+}
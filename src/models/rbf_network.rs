@@ -0,0 +1,386 @@
+// rbf_network.rs
+
+//! This file implements a radial basis function (RBF) network: a hidden layer of Gaussian units
+//! centered on prototypes of the training values and a linear output layer. The centers are
+//! either the centers of a k-means clustering of the features or the training values kept by the
+//! condensed nearest neighbor rule. Every unit shares the same width, by default the largest
+//! distance between two centers over √(2·number of centers). The output weights are fit by
+//! (slightly regularized) least squares on the unit activations plus a bias: one output per class
+//! regressing the class indicator for classification, or a single output for regression.
+
+use super::kmeans;
+use super::knn::KNearestNeighbor;
+use super::knn_condensed;
+use super::linalg;
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Centers {
+    KMeans,
+    Condensed,
+}
+
+impl Centers {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "kmeans" => Ok(Self::KMeans),
+            "knn-condensed" => Ok(Self::Condensed),
+            _ => Err(format!("Invalid centers {}, expected kmeans or knn-condensed", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for Centers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::KMeans => "kmeans",
+            Self::Condensed => "knn-condensed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RbfNetwork {
+    pub label_index: usize,
+    pub centers: Centers,
+    // Number of k-means clusters
+    pub num_centers: usize,
+    // Width of the Gaussian units, derived from the spread of the centers when missing
+    pub width: Option<f64>,
+    // Added to the diagonal of the least squares system so it always has a solution
+    pub regularization: f64,
+    pub max_iterations: usize,
+    pub seed: u64,
+    classification: bool,
+    // Class codes of the label column, sorted by value, one output per class
+    classes: Vec<Numeric>,
+    prototypes: Vec<Vec<Numeric>>,
+    resolved_width: f64,
+    // Weights of every output over the unit activations, the last one is the bias
+    weights: Vec<Vec<f64>>,
+}
+
+impl Model for RbfNetwork {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        if !self.classification {
+            return self.predict(sample);
+        }
+
+        // Return the class with the largest output, ties go to the smallest class
+        let outputs = self.outputs(sample);
+        let best = outputs
+            .iter()
+            .enumerate()
+            .fold(0, |best, (position, &output)| {
+                if output > outputs[best] {
+                    position
+                } else {
+                    best
+                }
+            });
+        self.classes[best]
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        if self.classification {
+            return self.label(sample);
+        }
+        self.outputs(sample)[0]
+    }
+
+    fn type_id(&self) -> &'static str {
+        "RbfNetwork"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("centers".into(), self.centers.to_string()),
+            ("num_centers".into(), self.num_centers.to_string()),
+            (
+                "width".into(),
+                self.width.map_or("auto".to_string(), |width| width.to_string()),
+            ),
+            ("regularization".into(), self.regularization.to_string()),
+            ("max_iterations".into(), self.max_iterations.to_string()),
+            ("seed".into(), self.seed.to_string()),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "centers" => {
+                    self.centers = Centers::from_name(val)?;
+                }
+                "num_centers" => {
+                    self.num_centers = val.parse::<usize>()?;
+                    if self.num_centers == 0 {
+                        return Err("The network needs at least one center!".into());
+                    }
+                }
+                "width" => {
+                    self.width = match val.as_str() {
+                        "auto" => None,
+                        _ => Some(val.parse::<f64>()?),
+                    };
+                }
+                "regularization" => {
+                    self.regularization = val.parse::<f64>()?;
+                }
+                "max_iterations" => {
+                    self.max_iterations = val.parse::<usize>()?;
+                }
+                "seed" => {
+                    self.seed = val.parse::<u64>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RbfNetwork {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            centers: Centers::KMeans,
+            num_centers: 10,
+            width: None,
+            regularization: 1e-8,
+            max_iterations: 100,
+            seed: 0,
+            classification: true,
+            classes: Vec::new(),
+            prototypes: Vec::new(),
+            resolved_width: 1.0,
+            weights: Vec::new(),
+        }
+    }
+
+    /// Places the centers and fits the output weights. Must be called after the hyperparameters
+    /// are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.classification = match context.task.as_deref() {
+            Some("classification") | None => true,
+            Some("regression") => false,
+            Some(_) => {
+                return Err(
+                    "Invalid model task, only regression and classification are supported".into(),
+                )
+            }
+        };
+
+        let rows = training_values
+            .iter()
+            .map(|sample| self.features(sample))
+            .collect::<Vec<Vec<Numeric>>>();
+
+        self.prototypes = match self.centers {
+            Centers::KMeans => {
+                let mut rng = StdRng::seed_from_u64(self.seed);
+                let initial = kmeans::plus_plus(&rows, self.num_centers, &mut rng);
+                let (centers, _, inertia) = kmeans::lloyd(&rows, initial, self.max_iterations);
+                println!("K-means centers inertia: {}", inertia);
+                centers
+            }
+            Centers::Condensed => {
+                // Condensing keeps the training values a one nearest neighbor rule needs
+                let epsilon = if self.classification {
+                    NUMERIC_DIGIT_PRECISION
+                } else {
+                    let labels = training_values
+                        .iter()
+                        .map(|sample| sample[self.label_index])
+                        .collect::<Vec<Numeric>>();
+                    let mean = labels.iter().sum::<f64>() / labels.len() as f64;
+                    let variance = labels.iter().map(|label| (label - mean).powi(2)).sum::<f64>()
+                        / labels.len() as f64;
+                    variance.sqrt()
+                };
+                let mut knn =
+                    KNearestNeighbor::new(training_values.to_vec(), self.label_index, 1, epsilon, 1.0);
+                knn.fit(training_values, context)?;
+                let active = knn_condensed::condense(&knn, training_values, epsilon);
+                rows.iter()
+                    .zip(active.iter())
+                    .filter(|(_, &active)| active)
+                    .map(|(row, _)| row.clone())
+                    .collect()
+            }
+        };
+
+        self.resolved_width = match self.width {
+            Some(width) => width,
+            None => {
+                let max_distance = self
+                    .prototypes
+                    .iter()
+                    .flat_map(|x| self.prototypes.iter().map(move |z| kmeans::squared_distance(x, z)))
+                    .fold(0.0, f64::max)
+                    .sqrt();
+                if max_distance > 0.0 {
+                    max_distance / (2.0 * self.prototypes.len() as f64).sqrt()
+                } else {
+                    1.0
+                }
+            }
+        };
+
+        // One target column per class indicator, or the label itself for regression
+        let targets = if self.classification {
+            self.classes = context
+                .classes(self.label_index)
+                .unwrap_or_else(|| training_classes(training_values, self.label_index));
+            self.classes
+                .iter()
+                .map(|class| {
+                    training_values
+                        .iter()
+                        .map(|sample| {
+                            if (sample[self.label_index] - class).abs() < NUMERIC_DIGIT_PRECISION {
+                                1.0
+                            } else {
+                                0.0
+                            }
+                        })
+                        .collect()
+                })
+                .collect::<Vec<Vec<f64>>>()
+        } else {
+            vec![training_values
+                .iter()
+                .map(|sample| sample[self.label_index])
+                .collect::<Vec<f64>>()]
+        };
+
+        let activations = rows
+            .iter()
+            .map(|row| self.activations(row))
+            .collect::<Vec<Vec<f64>>>();
+        self.weights = targets
+            .iter()
+            .map(|target| {
+                let (mut gram, moments) = linalg::normal_equations(&activations, target);
+                for (unit, row) in gram.iter_mut().enumerate() {
+                    row[unit] += self.regularization;
+                }
+                linalg::solve(gram, moments)
+            })
+            .collect::<Result<Vec<Vec<f64>>, Box<dyn Error>>>()?;
+
+        Ok(())
+    }
+
+    // Returns the activation of every unit plus a constant one for the bias
+    fn activations(&self, features: &[Numeric]) -> Vec<f64> {
+        let denominator = 2.0 * self.resolved_width.powi(2);
+        self.prototypes
+            .iter()
+            .map(|prototype| (-kmeans::squared_distance(prototype, features) / denominator).exp())
+            .chain(std::iter::once(1.0))
+            .collect()
+    }
+
+    fn outputs(&self, sample: &[Numeric]) -> Vec<f64> {
+        let activations = self.activations(&self.features(sample));
+        self.weights
+            .iter()
+            .map(|weights| {
+                weights
+                    .iter()
+                    .zip(activations.iter())
+                    .map(|(weight, activation)| weight * activation)
+                    .sum()
+            })
+            .collect()
+    }
+
+    // Returns the feature values of a sample, skipping the label
+    fn features(&self, sample: &[Numeric]) -> Vec<Numeric> {
+        sample
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| column != self.label_index)
+            .map(|(_, value)| *value)
+            .collect()
+    }
+}
+
+pub struct RbfNetworkTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for RbfNetworkTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = RbfNetwork::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built RBF network with {} {} centers of width {}",
+            model.prototypes.len(),
+            model.centers,
+            model.resolved_width
+        );
+
+        Ok(Box::new(model))
+    }
+}
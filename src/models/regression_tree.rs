@@ -0,0 +1,456 @@
+// regression_tree.rs
+
+//! This file implements a CART regression tree. Nodes are split in two at the feature threshold
+//! with the largest reduction of the squared error, and leaves predict the mean target value of
+//! their training examples. Growth stops at the maximum depth, when a split would leave fewer than
+//! `min_samples_leaf` examples on one side, or when the best split reduces the mean squared error
+//! of the tree by less than `min_impurity_decrease`. The grown tree can be pruned with
+//! cost-complexity pruning, either with a fixed `ccp_alpha` or with the alpha that gives the
+//! lowest error when cross-validated with the partitioner in the configuration file.
+
+use super::decision_tree::{split_columns, Split, TreeNode};
+use super::{Model, ModelBuilder, TrainingContext};
+
+use crate::data::data_frame::DataFrame;
+use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+use crate::validation;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum CostComplexity {
+    None,
+    // Alpha is chosen by cross-validation with the configured partitioner
+    Auto,
+    Alpha(Numeric),
+}
+
+impl CostComplexity {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "none" => Ok(Self::None),
+            "auto" => Ok(Self::Auto),
+            _ => {
+                let alpha = name.parse::<Numeric>()?;
+                if alpha < 0.0 {
+                    return Err("The cost-complexity alpha can't be negative!".into());
+                }
+                Ok(Self::Alpha(alpha))
+            }
+        }
+    }
+}
+
+impl fmt::Display for CostComplexity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Auto => write!(f, "auto"),
+            Self::Alpha(alpha) => write!(f, "{}", alpha),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegressionTree {
+    pub label_index: usize,
+    pub max_depth: Option<usize>,
+    pub min_samples_leaf: usize,
+    pub min_impurity_decrease: f64,
+    pub ccp_alpha: CostComplexity,
+    // Number of features considered at each split, all of them when missing
+    pub max_features: Option<usize>,
+    root: TreeNode,
+}
+
+impl Model for RegressionTree {
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        self.root.find(sample).value
+    }
+
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        self.root.find(sample).value
+    }
+
+    fn type_id(&self) -> &'static str {
+        "RegressionTree"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            (
+                "max_depth".into(),
+                self.max_depth
+                    .map_or("none".to_string(), |max_depth| max_depth.to_string()),
+            ),
+            ("min_samples_leaf".into(), self.min_samples_leaf.to_string()),
+            (
+                "min_impurity_decrease".into(),
+                self.min_impurity_decrease.to_string(),
+            ),
+            ("ccp_alpha".into(), self.ccp_alpha.to_string()),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "max_depth" => {
+                    self.max_depth = match val.as_str() {
+                        "none" => None,
+                        _ => Some(val.parse::<usize>()?),
+                    };
+                }
+                "min_samples_leaf" => {
+                    self.min_samples_leaf = val.parse::<usize>()?.max(1);
+                }
+                "min_impurity_decrease" => {
+                    self.min_impurity_decrease = val.parse::<f64>()?;
+                }
+                "ccp_alpha" => {
+                    self.ccp_alpha = CostComplexity::from_name(val)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl RegressionTree {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            max_depth: None,
+            min_samples_leaf: 1,
+            min_impurity_decrease: 0.0,
+            ccp_alpha: CostComplexity::None,
+            max_features: None,
+            root: TreeNode::leaf(0.0, Vec::new(), 0, 0.0),
+        }
+    }
+
+    /// Grows the tree on the training values and prunes it when cost-complexity pruning is
+    /// enabled. Must be called after the hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.root = self.grow_tree(training_values);
+
+        let alpha = match self.ccp_alpha {
+            CostComplexity::None => return Ok(()),
+            CostComplexity::Alpha(alpha) => alpha,
+            CostComplexity::Auto => self.cross_validate_alpha(training_values, context)?,
+        };
+
+        let num_leaves = self.root.num_leaves();
+        prune(&mut self.root, alpha);
+        println!(
+            "Cost-complexity pruning with alpha {}: {} -> {} leaves",
+            alpha,
+            num_leaves,
+            self.root.num_leaves()
+        );
+        Ok(())
+    }
+
+    pub fn root(&self) -> &TreeNode {
+        &self.root
+    }
+
+    fn grow_tree(&self, training_values: &[Box<[Numeric]>]) -> TreeNode {
+        let grower = Grower {
+            training_values,
+            label_index: self.label_index,
+            max_depth: self.max_depth,
+            min_samples_leaf: self.min_samples_leaf,
+            max_features: self.max_features,
+            // The threshold is on the mean squared error of the whole tree, so it is scaled to a
+            // sum of squared errors
+            min_error_decrease: self.min_impurity_decrease * training_values.len() as f64,
+        };
+        grower.grow((0..training_values.len()).collect(), 0)
+    }
+
+    // Grows a tree on the training part of every fold and returns the alpha on the pruning path of
+    // the full tree with the lowest squared error over the validation parts
+    fn cross_validate_alpha(
+        &self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<Numeric, Box<dyn Error>> {
+        let partitioning = context
+            .partitioning
+            .as_ref()
+            .ok_or("No partitioning given to choose the cost-complexity alpha!")?;
+
+        // Like CART, try the geometric mean of consecutive alphas on the pruning path so each
+        // candidate falls inside the range where one subtree of the full tree is optimal
+        let path = pruning_path(&self.root);
+        let mut candidates = vec![0.0];
+        candidates.extend(path.windows(2).map(|alphas| (alphas[0] * alphas[1]).sqrt()));
+        candidates.extend(path.last());
+
+        let partition = validation::get_partitioner(&partitioning.strategy)?;
+        let table = DataFrame::from_rows(training_values.to_vec())?;
+        let folds = partition(&table, self.label_index, &partitioning.parameters)?;
+
+        let mut errors = vec![0.0; candidates.len()];
+        for (training_indexes, validation_indexes) in folds.iter() {
+            let fold_training = training_indexes
+                .iter()
+                .map(|&idx| training_values[idx].clone())
+                .collect::<Vec<_>>();
+            let fold_tree = self.grow_tree(&fold_training);
+
+            for (alpha, error) in candidates.iter().zip(errors.iter_mut()) {
+                let mut pruned = fold_tree.clone();
+                prune(&mut pruned, *alpha);
+                *error += validation_indexes
+                    .iter()
+                    .map(|&idx| {
+                        let sample = &training_values[idx];
+                        (pruned.find(sample).value - sample[self.label_index]).powi(2)
+                    })
+                    .sum::<f64>();
+            }
+        }
+
+        // On a tie the larger alpha wins since it gives the simpler tree
+        let (alpha, error) = candidates
+            .iter()
+            .zip(errors.iter())
+            .fold((0.0, Numeric::INFINITY), |best, (&alpha, &error)| {
+                if error <= best.1 {
+                    (alpha, error)
+                } else {
+                    best
+                }
+            });
+        println!(
+            "Cost-complexity alphas: {:?}\nCross-validated squared errors: {:?}\nChosen alpha: {} (error: {})",
+            candidates, errors, alpha, error
+        );
+
+        Ok(alpha)
+    }
+}
+
+// Holds the training data while the tree grows
+struct Grower<'a> {
+    training_values: &'a [Box<[Numeric]>],
+    label_index: usize,
+    max_depth: Option<usize>,
+    min_samples_leaf: usize,
+    max_features: Option<usize>,
+    min_error_decrease: f64,
+}
+
+impl Grower<'_> {
+    fn grow(&self, indexes: Vec<usize>, depth: usize) -> TreeNode {
+        let (sum, sum_squares) = indexes.iter().fold((0.0, 0.0), |(sum, sum_squares), &idx| {
+            let target = self.training_values[idx][self.label_index];
+            (sum + target, sum_squares + target * target)
+        });
+        let num_samples = indexes.len() as f64;
+        let error = (sum_squares - sum * sum / num_samples).max(0.0);
+        let mut node = TreeNode::leaf(sum / num_samples, Vec::new(), indexes.len(), error);
+
+        // Stop on constant nodes, small nodes or at the maximum depth
+        if error <= NUMERIC_DIGIT_PRECISION
+            || indexes.len() < 2 * self.min_samples_leaf
+            || self.max_depth.is_some_and(|max_depth| depth >= max_depth)
+        {
+            return node;
+        }
+
+        // Find the split with the largest reduction of the squared error
+        let num_columns = self.training_values[0].len();
+        let best = split_columns(num_columns, self.label_index, self.max_features)
+            .into_iter()
+            .filter_map(|column| self.best_threshold(&indexes, column, error))
+            .max_by(|(_, _, x), (_, _, y)| x.total_cmp(y));
+        let (column, threshold, decrease) = match best {
+            Some(best) => best,
+            None => return node,
+        };
+
+        // Stop early when the split doesn't reduce the error enough
+        if decrease <= NUMERIC_DIGIT_PRECISION || decrease < self.min_error_decrease {
+            return node;
+        }
+
+        let (below, above): (Vec<usize>, Vec<usize>) = indexes
+            .iter()
+            .partition(|&&idx| self.training_values[idx][column] <= threshold);
+        node.split = Some(Split::Threshold {
+            column,
+            threshold,
+            below: Box::new(self.grow(below, depth + 1)),
+            above: Box::new(self.grow(above, depth + 1)),
+        });
+        node
+    }
+
+    // Tries every threshold between consecutive values that leaves enough examples on both sides
+    // and returns the column, threshold and error reduction of the best one
+    fn best_threshold(
+        &self,
+        indexes: &[usize],
+        column: usize,
+        error: f64,
+    ) -> Option<(usize, Numeric, f64)> {
+        let mut sorted = indexes.to_vec();
+        sorted.sort_by(|&x, &y| {
+            self.training_values[x][column].total_cmp(&self.training_values[y][column])
+        });
+
+        let (total_sum, total_squares) = sorted.iter().fold((0.0, 0.0), |(sum, squares), &idx| {
+            let target = self.training_values[idx][self.label_index];
+            (sum + target, squares + target * target)
+        });
+
+        let mut below_sum = 0.0;
+        let mut below_squares = 0.0;
+        let mut best: Option<(usize, Numeric, f64)> = None;
+        for position in 0..sorted.len() - 1 {
+            let target = self.training_values[sorted[position]][self.label_index];
+            below_sum += target;
+            below_squares += target * target;
+
+            let num_below = position + 1;
+            let num_above = sorted.len() - num_below;
+            if num_below < self.min_samples_leaf || num_above < self.min_samples_leaf {
+                continue;
+            }
+            let value = self.training_values[sorted[position]][column];
+            let next_value = self.training_values[sorted[position + 1]][column];
+            if (next_value - value).abs() < NUMERIC_DIGIT_PRECISION {
+                continue;
+            }
+
+            let above_sum = total_sum - below_sum;
+            let above_squares = total_squares - below_squares;
+            let below_error = below_squares - below_sum * below_sum / num_below as f64;
+            let above_error = above_squares - above_sum * above_sum / num_above as f64;
+            let decrease = error - below_error - above_error;
+            if best.is_none_or(|(_, _, best)| decrease > best) {
+                best = Some((column, (value + next_value) / 2.0, decrease));
+            }
+        }
+        best
+    }
+}
+
+// Cost of keeping the subtree under a node instead of a leaf, per extra leaf. The weakest link
+// of the tree is the internal node with the smallest cost.
+fn link_strength(node: &TreeNode) -> Numeric {
+    (node.error - node.subtree_error()) / (node.num_leaves() - 1) as f64
+}
+
+fn weakest_link(node: &TreeNode) -> Option<Numeric> {
+    node.split.as_ref()?;
+    let weakest = node
+        .children()
+        .iter()
+        .filter_map(|child| weakest_link(child))
+        .fold(link_strength(node), Numeric::min);
+    Some(weakest)
+}
+
+// Returns the increasing alphas at which the weakest links of the tree are pruned, until only the
+// root is left
+fn pruning_path(root: &TreeNode) -> Vec<Numeric> {
+    let mut tree = root.clone();
+    let mut path = Vec::new();
+    while let Some(alpha) = weakest_link(&tree) {
+        path.push(alpha);
+        prune(&mut tree, alpha);
+    }
+    path
+}
+
+// Replaces subtrees with leaves, bottom up, when the squared error the subtree saves is no larger
+// than alpha for every extra leaf. This gives the smallest subtree minimizing the
+// cost-complexity error + alpha * leaves.
+fn prune(node: &mut TreeNode, alpha: Numeric) {
+    if node.split.is_none() {
+        return;
+    }
+    for child in node.children_mut() {
+        prune(child, alpha);
+    }
+    if link_strength(node) <= alpha + NUMERIC_DIGIT_PRECISION {
+        node.split = None;
+    }
+}
+
+pub struct RegressionTreeTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for RegressionTreeTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = RegressionTree::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built regression tree with {} leaves and depth {}:\n{}",
+            model.root().num_leaves(),
+            model.root().depth(),
+            model.root().describe(&self.context.columns, target_value_idx)
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -0,0 +1,493 @@
+// spatial_index.rs
+
+//! This file implements the indexes used by the k-nearest neighbor learners to find the closest
+//! examples to a sample without calculating the distance to every example. A KD-tree splits the
+//! space along one feature at a time and can only be used with metrics that add up differences
+//! feature by feature, a ball tree groups examples in hyperspheres and works with any metric that
+//! follows the triangle inequality. Both return the exact nearest neighbors, or every example within
+//! a radius of the sample. Examples can be skipped during a query, which lets the edited and
+//! condensed trainers query a subset of the examples without rebuilding the index.
+
+use super::distance::Distance;
+use crate::types::Numeric;
+
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::fmt;
+
+// Above this number of features a KD-tree prunes poorly and a ball tree is used instead
+const KD_TREE_MAX_FEATURES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum IndexKind {
+    Auto,
+    KdTree,
+    BallTree,
+    BruteForce,
+}
+
+impl IndexKind {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "auto" => Ok(Self::Auto),
+            "kd-tree" => Ok(Self::KdTree),
+            "ball-tree" => Ok(Self::BallTree),
+            "brute-force" => Ok(Self::BruteForce),
+            _ => Err(format!("Unsupported neighbor index: {}", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for IndexKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Auto => "auto",
+            Self::KdTree => "kd-tree",
+            Self::BallTree => "ball-tree",
+            Self::BruteForce => "brute-force",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Candidate neighbor, ordered by distance and then by index so ties are broken deterministically
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    distance: Numeric,
+    idx: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then(self.idx.cmp(&other.idx))
+    }
+}
+
+// Keeps the k best candidates within the radius seen so far, the worst of them sits at the top of
+// the heap
+struct Neighborhood {
+    k: usize,
+    radius: Numeric,
+    heap: BinaryHeap<Candidate>,
+}
+
+impl Neighborhood {
+    fn new(k: usize, radius: Numeric) -> Self {
+        Self {
+            k,
+            radius,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    fn push(&mut self, candidate: Candidate) {
+        if candidate.distance > self.radius {
+            return;
+        }
+        if self.heap.len() < self.k {
+            self.heap.push(candidate);
+        } else if self.heap.peek().is_some_and(|worst| candidate < *worst) {
+            self.heap.pop();
+            self.heap.push(candidate);
+        }
+    }
+
+    // Distance any new candidate has to beat, the radius until the neighborhood is full
+    fn worst(&self) -> Numeric {
+        if self.heap.len() < self.k {
+            self.radius
+        } else {
+            self.heap
+                .peek()
+                .map_or(self.radius, |worst| worst.distance)
+        }
+    }
+
+    fn into_sorted(self) -> Vec<(usize, Numeric)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|candidate| (candidate.idx, candidate.distance))
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum KdNode {
+    Leaf(Vec<usize>),
+    Split {
+        column: usize,
+        value: Numeric,
+        left: Box<KdNode>,
+        right: Box<KdNode>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BallNode {
+    center: Box<[Numeric]>,
+    // Largest distance (in metric units) between the center and an example in the ball
+    radius: Numeric,
+    children: Option<(Box<BallNode>, Box<BallNode>)>,
+    indexes: Vec<usize>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum SpatialIndex {
+    #[default]
+    BruteForce,
+    KdTree(KdNode),
+    BallTree(BallNode),
+}
+
+impl SpatialIndex {
+    pub fn build(
+        kind: IndexKind,
+        examples: &[Box<[Numeric]>],
+        distance: &Distance,
+        label_index: usize,
+        leaf_size: usize,
+    ) -> Result<Self, Box<dyn Error>> {
+        let num_features = examples.first().map_or(0, |example| example.len() - 1);
+        let kind = match kind {
+            IndexKind::Auto
+                if distance.is_coordinate_wise() && num_features <= KD_TREE_MAX_FEATURES =>
+            {
+                IndexKind::KdTree
+            }
+            IndexKind::Auto if distance.is_metric() => IndexKind::BallTree,
+            IndexKind::Auto => IndexKind::BruteForce,
+            IndexKind::KdTree if !distance.is_coordinate_wise() => {
+                return Err(format!(
+                    "A KD-tree can't be used with the {} distance!",
+                    distance.metric
+                )
+                .into());
+            }
+            IndexKind::BallTree if !distance.is_metric() => {
+                return Err(format!(
+                    "A ball tree can't be used with the {} distance!",
+                    distance.metric
+                )
+                .into());
+            }
+            kind => kind,
+        };
+
+        if examples.is_empty() {
+            return Ok(Self::BruteForce);
+        }
+
+        let leaf_size = leaf_size.max(1);
+        let indexes = (0..examples.len()).collect();
+        let index = match kind {
+            IndexKind::KdTree => {
+                Self::KdTree(build_kd_node(examples, label_index, indexes, leaf_size))
+            }
+            IndexKind::BallTree => Self::BallTree(build_ball_node(
+                examples,
+                distance,
+                label_index,
+                indexes,
+                leaf_size,
+            )),
+            _ => Self::BruteForce,
+        };
+        Ok(index)
+    }
+
+    /// Returns the index and distance of the k examples closest to the sample, sorted by distance.
+    /// Examples for which `skip` returns true are ignored.
+    pub fn nearest(
+        &self,
+        examples: &[Box<[Numeric]>],
+        distance: &Distance,
+        label_index: usize,
+        sample: &[Numeric],
+        k: usize,
+        skip: &dyn Fn(usize) -> bool,
+    ) -> Vec<(usize, Numeric)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        self.query(
+            examples,
+            distance,
+            label_index,
+            sample,
+            Neighborhood::new(k, Numeric::INFINITY),
+            skip,
+        )
+    }
+
+    /// Returns the index and distance of every example within the radius of the sample, sorted by
+    /// distance. The radius is in the same units as the distance, so it is a squared radius for
+    /// the squared euclidean distance. Examples for which `skip` returns true are ignored.
+    pub fn within(
+        &self,
+        examples: &[Box<[Numeric]>],
+        distance: &Distance,
+        label_index: usize,
+        sample: &[Numeric],
+        radius: Numeric,
+        skip: &dyn Fn(usize) -> bool,
+    ) -> Vec<(usize, Numeric)> {
+        self.query(
+            examples,
+            distance,
+            label_index,
+            sample,
+            Neighborhood::new(usize::MAX, radius),
+            skip,
+        )
+    }
+
+    fn query(
+        &self,
+        examples: &[Box<[Numeric]>],
+        distance: &Distance,
+        label_index: usize,
+        sample: &[Numeric],
+        mut neighborhood: Neighborhood,
+        skip: &dyn Fn(usize) -> bool,
+    ) -> Vec<(usize, Numeric)> {
+        let search = Search {
+            examples,
+            distance,
+            label_index,
+            sample,
+            skip,
+        };
+        match self {
+            Self::BruteForce => search.scan(&mut neighborhood, 0..examples.len()),
+            Self::KdTree(root) => search.kd_tree(&mut neighborhood, root),
+            Self::BallTree(root) => search.ball_tree(&mut neighborhood, root),
+        }
+        neighborhood.into_sorted()
+    }
+}
+
+struct Search<'a> {
+    examples: &'a [Box<[Numeric]>],
+    distance: &'a Distance,
+    label_index: usize,
+    sample: &'a [Numeric],
+    skip: &'a dyn Fn(usize) -> bool,
+}
+
+impl Search<'_> {
+    fn scan(&self, neighborhood: &mut Neighborhood, indexes: impl Iterator<Item = usize>) {
+        for idx in indexes.filter(|&idx| !(self.skip)(idx)) {
+            let distance =
+                self.distance
+                    .between(self.sample, &self.examples[idx], self.label_index);
+            neighborhood.push(Candidate { distance, idx });
+        }
+    }
+
+    fn kd_tree(&self, neighborhood: &mut Neighborhood, node: &KdNode) {
+        match node {
+            KdNode::Leaf(indexes) => self.scan(neighborhood, indexes.iter().copied()),
+            KdNode::Split {
+                column,
+                value,
+                left,
+                right,
+            } => {
+                // Visit the side of the split the sample falls in first
+                let difference = self.sample[*column] - value;
+                let (near, far) = if difference < 0.0 {
+                    (left, right)
+                } else {
+                    (right, left)
+                };
+                self.kd_tree(neighborhood, near);
+
+                // Every example on the other side is at least this far along the split feature
+                let bound =
+                    self.distance
+                        .axis_distance(*column, self.label_index, difference.abs());
+                if bound <= neighborhood.worst() {
+                    self.kd_tree(neighborhood, far);
+                }
+            }
+        }
+    }
+
+    fn ball_tree(&self, neighborhood: &mut Neighborhood, node: &BallNode) {
+        // By the triangle inequality no example in the ball is closer than this
+        let center_distance = self.distance.to_metric(self.distance.between(
+            self.sample,
+            &node.center,
+            self.label_index,
+        ));
+        let bound = (center_distance - node.radius).max(0.0);
+        if bound > self.distance.to_metric(neighborhood.worst()) {
+            return;
+        }
+
+        match &node.children {
+            None => self.scan(neighborhood, node.indexes.iter().copied()),
+            Some((left, right)) => {
+                // Visit the child with the closest center first
+                let left_distance =
+                    self.distance
+                        .between(self.sample, &left.center, self.label_index);
+                let right_distance =
+                    self.distance
+                        .between(self.sample, &right.center, self.label_index);
+                if left_distance <= right_distance {
+                    self.ball_tree(neighborhood, left);
+                    self.ball_tree(neighborhood, right);
+                } else {
+                    self.ball_tree(neighborhood, right);
+                    self.ball_tree(neighborhood, left);
+                }
+            }
+        }
+    }
+}
+
+// Finds the feature column with the largest spread of values and splits the examples at its median
+fn split_widest(
+    examples: &[Box<[Numeric]>],
+    label_index: usize,
+    mut indexes: Vec<usize>,
+) -> Option<(usize, Numeric, Vec<usize>, Vec<usize>)> {
+    let num_columns = examples[indexes[0]].len();
+    let (column, spread) = (0..num_columns)
+        .filter(|&column| column != label_index)
+        .map(|column| {
+            let (smallest, biggest) = indexes.iter().fold(
+                (Numeric::INFINITY, Numeric::NEG_INFINITY),
+                |(smallest, biggest), &idx| {
+                    (
+                        smallest.min(examples[idx][column]),
+                        biggest.max(examples[idx][column]),
+                    )
+                },
+            );
+            (column, biggest - smallest)
+        })
+        .max_by(|(_, x), (_, y)| x.total_cmp(y))?;
+
+    // All the examples are at the same point, there is nothing to split
+    if spread <= 0.0 {
+        return None;
+    }
+
+    let middle = indexes.len() / 2;
+    indexes.select_nth_unstable_by(middle, |&x, &y| {
+        examples[x][column].total_cmp(&examples[y][column])
+    });
+    let value = examples[indexes[middle]][column];
+    let right = indexes.split_off(middle);
+    Some((column, value, indexes, right))
+}
+
+fn build_kd_node(
+    examples: &[Box<[Numeric]>],
+    label_index: usize,
+    indexes: Vec<usize>,
+    leaf_size: usize,
+) -> KdNode {
+    if indexes.len() <= leaf_size {
+        return KdNode::Leaf(indexes);
+    }
+    match split_widest(examples, label_index, indexes.clone()) {
+        Some((column, value, left, right)) => KdNode::Split {
+            column,
+            value,
+            left: Box::new(build_kd_node(examples, label_index, left, leaf_size)),
+            right: Box::new(build_kd_node(examples, label_index, right, leaf_size)),
+        },
+        None => KdNode::Leaf(indexes),
+    }
+}
+
+fn build_ball_node(
+    examples: &[Box<[Numeric]>],
+    distance: &Distance,
+    label_index: usize,
+    indexes: Vec<usize>,
+    leaf_size: usize,
+) -> BallNode {
+    // Use the example closest to the centroid as the center, so the center is always a valid point
+    // for metrics over categorical values
+    let num_columns = examples[indexes[0]].len();
+    let centroid = (0..num_columns)
+        .map(|column| {
+            indexes
+                .iter()
+                .fold(0.0, |acc, &idx| acc + examples[idx][column])
+                / indexes.len() as f64
+        })
+        .collect::<Vec<Numeric>>();
+    let center_idx = *indexes
+        .iter()
+        .min_by(|&&x, &&y| {
+            distance
+                .between(&centroid, &examples[x], label_index)
+                .total_cmp(&distance.between(&centroid, &examples[y], label_index))
+        })
+        .unwrap();
+    let center = examples[center_idx].clone();
+    let radius = indexes.iter().fold(0.0, |acc: Numeric, &idx| {
+        acc.max(distance.to_metric(distance.between(&center, &examples[idx], label_index)))
+    });
+
+    let children = if indexes.len() > leaf_size {
+        split_widest(examples, label_index, indexes.clone()).map(|(_, _, left, right)| {
+            (
+                Box::new(build_ball_node(
+                    examples,
+                    distance,
+                    label_index,
+                    left,
+                    leaf_size,
+                )),
+                Box::new(build_ball_node(
+                    examples,
+                    distance,
+                    label_index,
+                    right,
+                    leaf_size,
+                )),
+            )
+        })
+    } else {
+        None
+    };
+
+    BallNode {
+        center,
+        radius,
+        indexes: if children.is_none() {
+            indexes
+        } else {
+            Vec::new()
+        },
+        children,
+    }
+}
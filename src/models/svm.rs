@@ -0,0 +1,513 @@
+// svm.rs
+
+//! This file implements support vector machines trained with Sequential Minimal Optimization
+//! (SMO). Both the soft margin classifier and epsilon-insensitive regression (epsilon-SVR) are
+//! written as the same dual problem: minimize ½αᵀQα + pᵀα subject to 0 ≤ α ≤ C and yᵀα = 0,
+//! where Qᵢⱼ = yᵢyⱼK(xᵢ, xⱼ). SMO repeatedly picks the pair of multipliers that violates the
+//! optimality conditions the most (the second one chosen by the largest decrease of the
+//! objective) and solves the problem for that pair analytically, until the violation is below the
+//! tolerance. Multiclass problems train one machine per class against the rest and predict the
+//! class whose machine gives the largest decision value. The kernel matrix of the training values
+//! is computed once, so memory grows with the square of the training set size. Kernels are
+//! sensitive to the scale of the features, which should be standardized e.g. with a z-score
+//! transform.
+
+use super::{training_classes, Model, ModelBuilder, TrainingContext};
+
+use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+// Curvature used when a pair of multipliers has none, as in LIBSVM
+const TAU: f64 = 1e-12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Kernel {
+    Linear,
+    Polynomial,
+    Rbf,
+}
+
+impl Kernel {
+    pub fn from_name(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "linear" => Ok(Self::Linear),
+            "poly" | "polynomial" => Ok(Self::Polynomial),
+            "rbf" => Ok(Self::Rbf),
+            _ => Err(format!("Invalid kernel {}, expected linear, poly or rbf", name).into()),
+        }
+    }
+}
+
+impl fmt::Display for Kernel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Linear => "linear",
+            Self::Polynomial => "poly",
+            Self::Rbf => "rbf",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Decision function of one trained machine: Σ coefficientᵢ·K(xᵢ, x) - rho
+#[derive(Debug, Serialize, Deserialize)]
+struct Machine {
+    support_vectors: Vec<Vec<Numeric>>,
+    coefficients: Vec<f64>,
+    rho: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupportVectorMachine {
+    pub label_index: usize,
+    pub kernel: Kernel,
+    pub c: f64,
+    // Kernel coefficient of the polynomial and rbf kernels, 1 / number of features when missing
+    pub gamma: Option<f64>,
+    pub degree: i32,
+    pub coef0: f64,
+    pub epsilon: f64,
+    pub tolerance: f64,
+    pub max_iterations: usize,
+    classification: bool,
+    // Class codes of the label column, sorted by value, one machine per class
+    classes: Vec<Numeric>,
+    machines: Vec<Machine>,
+    resolved_gamma: f64,
+}
+
+impl Model for SupportVectorMachine {
+    fn label(&self, sample: &[Numeric]) -> Numeric {
+        if !self.classification {
+            return self.predict(sample);
+        }
+
+        // Return the class whose machine is the most confident, ties go to the smallest class
+        let features = self.features(sample);
+        let decisions = self
+            .machines
+            .iter()
+            .map(|machine| self.decision(machine, &features))
+            .collect::<Vec<f64>>();
+        let best = decisions
+            .iter()
+            .enumerate()
+            .fold(0, |best, (position, &decision)| {
+                if decision > decisions[best] {
+                    position
+                } else {
+                    best
+                }
+            });
+        self.classes[best]
+    }
+
+    fn predict(&self, sample: &[Numeric]) -> Numeric {
+        if self.classification {
+            return self.label(sample);
+        }
+        self.decision(&self.machines[0], &self.features(sample))
+    }
+
+    fn type_id(&self) -> &'static str {
+        "SupportVectorMachine"
+    }
+
+    fn save_state(&self) -> Result<serde_yaml::Value, Box<dyn Error>> {
+        Ok(serde_yaml::to_value(self)?)
+    }
+
+    fn get_hyperparameters(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("kernel".into(), self.kernel.to_string()),
+            ("C".into(), self.c.to_string()),
+            (
+                "gamma".into(),
+                self.gamma.map_or("auto".to_string(), |gamma| gamma.to_string()),
+            ),
+            ("degree".into(), self.degree.to_string()),
+            ("coef0".into(), self.coef0.to_string()),
+            ("epsilon".into(), self.epsilon.to_string()),
+            ("tolerance".into(), self.tolerance.to_string()),
+            ("max_iterations".into(), self.max_iterations.to_string()),
+        ])
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: &HashMap<String, String>) -> Result<(), Box<dyn Error>> {
+        for (key, val) in hyperparameters.iter() {
+            match key.as_str() {
+                "kernel" => {
+                    self.kernel = Kernel::from_name(val)?;
+                }
+                "C" => {
+                    self.c = val.parse::<f64>()?;
+                    if self.c <= 0.0 {
+                        return Err("The penalty C must be positive!".into());
+                    }
+                }
+                "gamma" => {
+                    self.gamma = match val.as_str() {
+                        "auto" => None,
+                        _ => Some(val.parse::<f64>()?),
+                    };
+                }
+                "degree" => {
+                    self.degree = val.parse::<i32>()?;
+                }
+                "coef0" => {
+                    self.coef0 = val.parse::<f64>()?;
+                }
+                "epsilon" => {
+                    self.epsilon = val.parse::<f64>()?;
+                }
+                "tolerance" => {
+                    self.tolerance = val.parse::<f64>()?;
+                }
+                "max_iterations" => {
+                    self.max_iterations = val.parse::<usize>()?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SupportVectorMachine {
+    pub fn new(label_index: usize) -> Self {
+        Self {
+            label_index,
+            kernel: Kernel::Rbf,
+            c: 1.0,
+            gamma: None,
+            degree: 3,
+            coef0: 0.0,
+            epsilon: 0.1,
+            tolerance: 1e-3,
+            max_iterations: 100000,
+            classification: true,
+            classes: Vec::new(),
+            machines: Vec::new(),
+            resolved_gamma: 1.0,
+        }
+    }
+
+    /// Trains one machine per class, or a single regression machine. Must be called after the
+    /// hyperparameters are set.
+    pub fn fit(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        context: &TrainingContext,
+    ) -> Result<(), Box<dyn Error>> {
+        self.classification = match context.task.as_deref() {
+            Some("classification") | None => true,
+            Some("regression") => false,
+            Some(_) => {
+                return Err(
+                    "Invalid model task, only regression and classification are supported".into(),
+                )
+            }
+        };
+
+        let rows = training_values
+            .iter()
+            .map(|sample| self.features(sample))
+            .collect::<Vec<Vec<Numeric>>>();
+        let targets = training_values
+            .iter()
+            .map(|sample| sample[self.label_index])
+            .collect::<Vec<Numeric>>();
+        let num_samples = rows.len();
+        self.resolved_gamma = self
+            .gamma
+            .unwrap_or(1.0 / rows[0].len().max(1) as f64);
+
+        let kernel_matrix = rows
+            .iter()
+            .map(|x| rows.iter().map(|z| self.kernel_value(x, z)).collect())
+            .collect::<Vec<Vec<f64>>>();
+
+        self.machines.clear();
+        if self.classification {
+            self.classes = context
+                .classes(self.label_index)
+                .unwrap_or_else(|| training_classes(training_values, self.label_index));
+            for &class in self.classes.iter() {
+                // The class against the rest, every multiplier starts with a unit linear term
+                let signs = targets
+                    .iter()
+                    .map(|target| {
+                        if (target - class).abs() < NUMERIC_DIGIT_PRECISION {
+                            1.0
+                        } else {
+                            -1.0
+                        }
+                    })
+                    .collect::<Vec<f64>>();
+                let linear = vec![-1.0; num_samples];
+                let machine = self.solve(&rows, &kernel_matrix, &signs, &linear);
+                println!(
+                    "Class {}: {} support vectors",
+                    class,
+                    machine.support_vectors.len()
+                );
+                self.machines.push(machine);
+            }
+        } else {
+            // One multiplier for the samples above the tube and one for the samples below it
+            let signs = [vec![1.0; num_samples], vec![-1.0; num_samples]].concat();
+            let linear = targets
+                .iter()
+                .map(|target| self.epsilon - target)
+                .chain(targets.iter().map(|target| self.epsilon + target))
+                .collect::<Vec<f64>>();
+            let machine = self.solve(&rows, &kernel_matrix, &signs, &linear);
+            println!("Regression: {} support vectors", machine.support_vectors.len());
+            self.machines.push(machine);
+        }
+
+        Ok(())
+    }
+
+    // Solves the dual problem by SMO. Multiplier k belongs to the training value k modulo the
+    // number of training values, `signs` gives yₖ and `linear` gives pₖ.
+    fn solve(
+        &self,
+        rows: &[Vec<Numeric>],
+        kernel_matrix: &[Vec<f64>],
+        signs: &[f64],
+        linear: &[f64],
+    ) -> Machine {
+        let num_samples = rows.len();
+        let num_variables = signs.len();
+        let kernel = |u: usize, v: usize| kernel_matrix[u % num_samples][v % num_samples];
+
+        let mut alphas = vec![0.0; num_variables];
+        // Gradient of the objective, Qα + p
+        let mut gradient = linear.to_vec();
+
+        // Multipliers that can move up (I_up) or down (I_low) along yₖ
+        let in_up = |alpha: f64, sign: f64| (sign > 0.0 && alpha < self.c) || (sign < 0.0 && alpha > 0.0);
+        let in_low = |alpha: f64, sign: f64| (sign > 0.0 && alpha > 0.0) || (sign < 0.0 && alpha < self.c);
+
+        let mut iterations = 0;
+        while iterations < self.max_iterations {
+            iterations += 1;
+
+            // First multiplier: the largest violation among the ones that can move up
+            let mut max_violation = f64::NEG_INFINITY;
+            let mut first = None;
+            for k in 0..num_variables {
+                if in_up(alphas[k], signs[k]) && -signs[k] * gradient[k] > max_violation {
+                    max_violation = -signs[k] * gradient[k];
+                    first = Some(k);
+                }
+            }
+            let Some(i) = first else { break };
+
+            // Second multiplier: the largest decrease of the objective among the ones that can
+            // move down
+            let mut min_violation = f64::INFINITY;
+            let mut best_decrease = f64::INFINITY;
+            let mut second = None;
+            for k in 0..num_variables {
+                if !in_low(alphas[k], signs[k]) {
+                    continue;
+                }
+                let violation = -signs[k] * gradient[k];
+                min_violation = min_violation.min(violation);
+                let gap = max_violation - violation;
+                if gap > 0.0 {
+                    let curvature = kernel(i, i) + kernel(k, k) - 2.0 * kernel(i, k);
+                    let decrease = -gap * gap / curvature.max(TAU);
+                    if decrease < best_decrease {
+                        best_decrease = decrease;
+                        second = Some(k);
+                    }
+                }
+            }
+            if max_violation - min_violation < self.tolerance {
+                break;
+            }
+            let Some(j) = second else { break };
+
+            // Move αᵢ up and αⱼ down along their signs by the optimal step, within the box
+            let gap = max_violation + signs[j] * gradient[j];
+            let curvature = (kernel(i, i) + kernel(j, j) - 2.0 * kernel(i, j)).max(TAU);
+            let limit_i = if signs[i] > 0.0 { self.c - alphas[i] } else { alphas[i] };
+            let limit_j = if signs[j] > 0.0 { alphas[j] } else { self.c - alphas[j] };
+            let step = (gap / curvature).min(limit_i).min(limit_j);
+
+            alphas[i] = clip(alphas[i] + signs[i] * step, self.c);
+            alphas[j] = clip(alphas[j] - signs[j] * step, self.c);
+            for k in 0..num_variables {
+                gradient[k] += signs[k] * step * (kernel(k, i) - kernel(k, j));
+            }
+        }
+        if iterations >= self.max_iterations {
+            println!("SMO stopped after {} iterations without converging", iterations);
+        }
+
+        // The bias is the average over the free multipliers, or the middle of its feasible range
+        // when every multiplier is at a bound
+        let mut upper = f64::INFINITY;
+        let mut lower = f64::NEG_INFINITY;
+        let mut free_sum = 0.0;
+        let mut num_free = 0;
+        for k in 0..num_variables {
+            let value = signs[k] * gradient[k];
+            if alphas[k] >= self.c {
+                if signs[k] < 0.0 {
+                    upper = upper.min(value);
+                } else {
+                    lower = lower.max(value);
+                }
+            } else if alphas[k] <= 0.0 {
+                if signs[k] > 0.0 {
+                    upper = upper.min(value);
+                } else {
+                    lower = lower.max(value);
+                }
+            } else {
+                free_sum += value;
+                num_free += 1;
+            }
+        }
+        let rho = if num_free > 0 {
+            free_sum / num_free as f64
+        } else {
+            (upper + lower) / 2.0
+        };
+
+        // Keep the training values with a non-zero coefficient
+        let mut coefficients = vec![0.0; num_samples];
+        for k in 0..num_variables {
+            coefficients[k % num_samples] += signs[k] * alphas[k];
+        }
+        let (support_vectors, coefficients) = rows
+            .iter()
+            .zip(coefficients)
+            .filter(|(_, coefficient)| *coefficient != 0.0)
+            .map(|(row, coefficient)| (row.clone(), coefficient))
+            .unzip();
+
+        Machine {
+            support_vectors,
+            coefficients,
+            rho,
+        }
+    }
+
+    fn decision(&self, machine: &Machine, features: &[Numeric]) -> f64 {
+        machine
+            .support_vectors
+            .iter()
+            .zip(machine.coefficients.iter())
+            .fold(-machine.rho, |acc, (support_vector, coefficient)| {
+                acc + coefficient * self.kernel_value(support_vector, features)
+            })
+    }
+
+    fn kernel_value(&self, x: &[Numeric], z: &[Numeric]) -> f64 {
+        match self.kernel {
+            Kernel::Linear => dot(x, z),
+            Kernel::Polynomial => (self.resolved_gamma * dot(x, z) + self.coef0).powi(self.degree),
+            Kernel::Rbf => {
+                let squared_distance = x
+                    .iter()
+                    .zip(z.iter())
+                    .map(|(a, b)| (a - b).powi(2))
+                    .sum::<f64>();
+                (-self.resolved_gamma * squared_distance).exp()
+            }
+        }
+    }
+
+    // Returns the feature values of a sample, skipping the label
+    fn features(&self, sample: &[Numeric]) -> Vec<Numeric> {
+        sample
+            .iter()
+            .enumerate()
+            .filter(|&(column, _)| column != self.label_index)
+            .map(|(_, value)| *value)
+            .collect()
+    }
+}
+
+fn dot(x: &[Numeric], z: &[Numeric]) -> f64 {
+    x.iter().zip(z.iter()).map(|(a, b)| a * b).sum()
+}
+
+// Snaps a multiplier that rounding left just outside or next to the box onto the bound
+fn clip(alpha: f64, c: f64) -> f64 {
+    if alpha >= c - TAU {
+        c
+    } else if alpha <= TAU {
+        0.0
+    } else {
+        alpha
+    }
+}
+
+pub struct SupportVectorMachineTrainer {
+    hyperparameters: Option<HashMap<String, String>>,
+    context: TrainingContext,
+}
+
+impl ModelBuilder for SupportVectorMachineTrainer {
+    fn new() -> Self
+    where
+        Self: Sized,
+    {
+        Self {
+            hyperparameters: None,
+            context: TrainingContext::default(),
+        }
+    }
+
+    fn with_hyperparameters(
+        &mut self,
+        features: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.hyperparameters = Some(features.clone());
+        Ok(())
+    }
+
+    fn with_context(&mut self, context: &TrainingContext) -> Result<(), Box<dyn Error>> {
+        self.context = context.clone();
+        Ok(())
+    }
+
+    fn build(
+        &mut self,
+        training_values: &[Box<[Numeric]>],
+        target_value_idx: usize,
+        _sample_weights: Option<&[Numeric]>,
+    ) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        if training_values.is_empty() {
+            return Err("Empty training set given!".into());
+        }
+        if target_value_idx >= training_values[0].len() {
+            return Err("Target value index is out of bounds!".into());
+        }
+
+        let mut model = SupportVectorMachine::new(target_value_idx);
+        if let Some(hyperparameters) = self.hyperparameters.as_ref() {
+            model.set_hyperparameters(hyperparameters)?;
+        }
+        model.fit(training_values, &self.context)?;
+
+        println!(
+            "Built {} kernel support vector machine with {} machines",
+            model.kernel,
+            model.machines.len()
+        );
+
+        Ok(Box::new(model))
+    }
+}
@@ -0,0 +1,89 @@
+// persistence.rs
+
+//! This module saves a trained model together with everything needed to use it on new data: how
+//! the input was read and parsed (the value maps of the ordinal/nominal columns give the label
+//! encoding), the values the scrubbers filled in, what the transforms learned, the columns kept by
+//! feature selection and the hyperparameters of the model. The file is YAML and carries a format
+//! version, files written with another version are refused instead of being misread.
+
+use crate::data::column::ColumnInfo;
+use crate::models::{Model, SavedModel};
+use crate::transform::FittedStage;
+use crate::types::Numeric;
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+
+/// Version of the saved model format, to be increased whenever the format changes
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Value a scrubber filled the missing values of a column with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrubbedColumn {
+    pub index: usize,
+    pub value: Numeric,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SavedPipeline {
+    pub version: u32,
+    // Input stage settings, new data must be read the same way
+    pub format: String,
+    pub missing_values: Vec<String>,
+    pub headers: bool,
+    pub parsing: Vec<String>,
+    // Name and value map of every parsed column
+    pub columns: Vec<ColumnInfo>,
+    pub scrub: Vec<ScrubbedColumn>,
+    pub transform: Vec<FittedStage>,
+    // Columns kept by feature selection, indexed in the table after the transforms
+    pub selection: Option<Vec<usize>>,
+    // Label column in the parsed table, and in the table the model was trained on
+    pub input_label_index: usize,
+    pub label_index: usize,
+    pub task: String,
+    pub model_name: String,
+    pub hyperparameters: HashMap<String, String>,
+    pub model: SavedModel,
+}
+
+impl SavedPipeline {
+    /// Rebuilds the trained model
+    pub fn model(&self) -> Result<Box<dyn Model>, Box<dyn Error>> {
+        self.model.load()
+    }
+
+    /// Returns the value map of the label column, if the label was parsed as ordinal or nominal
+    pub fn label_encoding(&self) -> Option<&HashMap<u32, String>> {
+        self.columns.get(self.input_label_index)?.metadata.as_ref()
+    }
+}
+
+pub fn save(path: &str, pipeline: &SavedPipeline) -> Result<(), Box<dyn Error>> {
+    // Serialize first so a model that can't be saved doesn't leave an empty file behind
+    let text = serde_yaml::to_string(pipeline)?;
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// Reads a saved model, checking the format version before anything else
+pub fn load(path: &str) -> Result<SavedPipeline, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let value: serde_yaml::Value = serde_yaml::from_reader(file)?;
+    let version = value
+        .get("version")
+        .and_then(|version| version.as_u64())
+        .ok_or(format!("{} is not a saved model, it has no format version!", path))?;
+    if version != FORMAT_VERSION as u64 {
+        return Err(format!(
+            "{} was saved with model format version {}, but this build reads version {}. Train \
+             and save the model again with this build.",
+            path, version, FORMAT_VERSION
+        )
+        .into());
+    }
+    Ok(serde_yaml::from_value(value)?)
+}
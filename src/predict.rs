@@ -0,0 +1,185 @@
+// predict.rs
+
+//! This module scores new data with a saved model. The new file has the columns of the training
+//! input without the label column. It is read, parsed, scrubbed, transformed and reduced to the
+//! selected columns the way the training data was, using the state saved with the model, and the
+//! predictions are written to a CSV file with one line per input row. Class predictions are
+//! written as the original strings of the label column, regression predictions are mapped back
+//! through the transforms applied to the label.
+
+use crate::input;
+use crate::models::Model;
+use crate::persistence::{self, SavedPipeline};
+use crate::transform::{FittedStage, FittedTransform};
+use crate::types::Numeric;
+
+use std::error::Error;
+use std::fs;
+
+// A sample ready for the model and the column transforms that were applied to its label
+pub type PreparedRow<'a> = (Vec<Numeric>, Vec<&'a FittedTransform>);
+
+pub fn predict(
+    model_path: &str,
+    input_path: &str,
+    output_path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let saved = persistence::load(model_path)?;
+    let model = saved.model()?;
+    println!("Loaded {} model from {}", saved.model_name, model_path);
+
+    // The new data is read the same way as the training data
+    let read = input::get_reader(&saved.format)?;
+    let table = read(input_path, &saved.missing_values, saved.headers)?;
+    let num_columns = table.columns().len();
+    if num_columns + 1 != saved.parsing.len() {
+        return Err(format!(
+            "The model was trained on {} columns, expected {} without the label but got {}!",
+            saved.parsing.len(),
+            saved.parsing.len() - 1,
+            num_columns
+        )
+        .into());
+    }
+    let num_rows = table.columns().next().map_or(0, |column| column.values().len());
+    println!("Read {} rows from {}", num_rows, input_path);
+
+    let label_name = saved
+        .columns
+        .get(saved.input_label_index)
+        .and_then(|column| column.name.clone())
+        .unwrap_or_else(|| "prediction".to_string());
+    let mut output = String::new();
+    if saved.headers {
+        output.push_str(&label_name);
+        output.push('\n');
+    }
+
+    let mut num_skipped = 0;
+    for idx in 0..num_rows {
+        let raw = table.get_row(idx)?;
+        match prepare_row(&saved, &raw)? {
+            Some((sample, label_transforms)) => {
+                let prediction = predict_sample(&saved, model.as_ref(), &sample, &label_transforms);
+                output.push_str(&prediction);
+            }
+            None => {
+                // Leave the line empty so the output lines still match the input rows
+                println!("Row {} has missing values no scrubber fills, it gets no prediction", idx);
+                num_skipped += 1;
+            }
+        }
+        output.push('\n');
+    }
+
+    fs::write(output_path, output)?;
+    println!(
+        "Wrote {} predictions to {}",
+        num_rows - num_skipped,
+        output_path
+    );
+
+    Ok(())
+}
+
+/// Runs a row of the new data through the saved stages. Returns the sample the model expects,
+/// with a placeholder label, and the column transforms applied to the label, or None when the row
+/// has a missing value no scrubber fills.
+pub fn prepare_row<'a>(
+    saved: &'a SavedPipeline,
+    raw: &[Option<String>],
+) -> Result<Option<PreparedRow<'a>>, Box<dyn Error>> {
+    // Parse every column but the label, ordinal/nominal strings are coded with the training maps
+    let mut values = raw.iter();
+    let mut parsed: Vec<Option<Numeric>> = Vec::new();
+    for parser in saved.parsing.iter() {
+        if parsed.len() == saved.input_label_index && parser != "null" {
+            parsed.push(Some(0.0));
+            continue;
+        }
+        let value = values.next().ok_or("The row is missing columns!")?;
+        let parsed_value = match parser.as_str() {
+            "null" => continue,
+            "numeric" => value.as_ref().and_then(|v| v.parse::<Numeric>().ok()),
+            "ordinal" | "nominal" => {
+                let column = saved
+                    .columns
+                    .get(parsed.len())
+                    .ok_or("The saved model has no value map for a column!")?;
+                // Strings never seen in training are treated as missing values
+                value.as_ref().and_then(|v| column.value_code(v))
+            }
+            _ => return Err(format!("Invalid parser name saved with the model: {}", parser).into()),
+        };
+        parsed.push(parsed_value);
+    }
+
+    // Fill the missing values with what the scrubbers filled in during training
+    for scrubbed in saved.scrub.iter() {
+        if let Some(value) = parsed.get_mut(scrubbed.index) {
+            *value = value.or(Some(scrubbed.value));
+        }
+    }
+    let mut row = match parsed.into_iter().collect::<Option<Vec<Numeric>>>() {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+
+    // Repeat the transforms, keeping track of the ones applied to the label column
+    let mut label_index = Some(saved.input_label_index);
+    let mut label_transforms = Vec::new();
+    for stage in saved.transform.iter() {
+        if let FittedStage::Column { index, transform } = stage {
+            if Some(*index) == label_index {
+                label_transforms.push(transform);
+            }
+        }
+        (row, label_index) = stage.apply_row(row, label_index)?;
+    }
+
+    // Keep the columns the feature selection kept
+    if let Some(selection) = saved.selection.as_ref() {
+        row = selection
+            .iter()
+            .map(|&idx| row.get(idx).copied())
+            .collect::<Option<Vec<Numeric>>>()
+            .ok_or("The row doesn't have the columns kept by feature selection!")?;
+    }
+
+    Ok(Some((row, label_transforms)))
+}
+
+/// Returns the prediction of the model for a sample as it is written to the output
+fn predict_sample(
+    saved: &SavedPipeline,
+    model: &dyn Model,
+    sample: &[Numeric],
+    label_transforms: &[&FittedTransform],
+) -> String {
+    if saved.task == "classification" {
+        class_name(saved, model.label(sample))
+    } else {
+        regression_value(model, sample, label_transforms).to_string()
+    }
+}
+
+/// Returns the original string of a class code, or the code itself if the label had no value map
+pub fn class_name(saved: &SavedPipeline, label: Numeric) -> String {
+    saved
+        .columns
+        .get(saved.input_label_index)
+        .and_then(|column| column.value_name(label))
+        .map_or_else(|| label.to_string(), |name| name.to_string())
+}
+
+/// Returns the regression prediction of the model in the units of the original label column
+pub fn regression_value(
+    model: &dyn Model,
+    sample: &[Numeric],
+    label_transforms: &[&FittedTransform],
+) -> Numeric {
+    label_transforms
+        .iter()
+        .rev()
+        .fold(model.predict(sample), |value, transform| transform.inverse(value))
+}
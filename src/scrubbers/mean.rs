@@ -11,7 +11,7 @@ use std::error::Error;
 pub struct MeanScrubber;
 
 impl Scrubber for MeanScrubber {
-    fn clean(column: &mut Column<Option<Numeric>>) -> Result<(), Box<dyn Error>> {
+    fn clean(column: &mut Column<Option<Numeric>>) -> Result<Numeric, Box<dyn Error>> {
         // Sum over all elements that are not None
         let sum = column
             .values()
@@ -32,6 +32,6 @@ impl Scrubber for MeanScrubber {
         // by the given value, in this case Some(mean).
         column.values_mut().for_each(|v| *v = v.or(Some(mean)));
         
-        Ok(())
+        Ok(mean)
     }
 }
@@ -9,11 +9,12 @@ use crate::types::Numeric;
 
 use std::error::Error;
 
+// Scrubbers return the value that replaced the missing values, so it can be reused on new data
 trait Scrubber {
-    fn clean(column: &mut Column<Option<Numeric>>) -> Result<(), Box<dyn Error>>;
+    fn clean(column: &mut Column<Option<Numeric>>) -> Result<Numeric, Box<dyn Error>>;
 }
 
-type ScrubFnPtr = fn(&mut Column<Option<Numeric>>) -> Result<(), Box<dyn Error>>;
+type ScrubFnPtr = fn(&mut Column<Option<Numeric>>) -> Result<Numeric, Box<dyn Error>>;
 
 pub fn get_scrubber(name: &str) -> Result<ScrubFnPtr, Box<dyn Error>> {
     match name {
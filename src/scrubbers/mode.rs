@@ -12,7 +12,7 @@ use std::error::Error;
 pub struct ModeScrubber;
 
 impl Scrubber for ModeScrubber {
-    fn clean(column: &mut Column<Option<Numeric>>) -> Result<(), Box<dyn Error>> {
+    fn clean(column: &mut Column<Option<Numeric>>) -> Result<Numeric, Box<dyn Error>> {
         let mut label_count = HashMap::new();
 
         // Populate map with the count of unique values in the input set
@@ -38,6 +38,6 @@ impl Scrubber for ModeScrubber {
         // by the given value, in this case Some(mode).
         column.values_mut().for_each(|v| *v = v.or(Some(mode)));
 
-        Ok(())
+        Ok(mode)
     }
 }
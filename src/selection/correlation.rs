@@ -0,0 +1,78 @@
+// correlation.rs
+
+//! This selector removes redundant feature columns. The Pearson correlation is calculated between
+//! every pair of feature columns and, when the absolute correlation of a pair is above a threshold,
+//! the column that comes later in the table is dropped.
+
+use super::Selector;
+use crate::config::TrainingConfigs;
+use crate::data::data_frame::DataFrame;
+use crate::types::Numeric;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct CorrelationFilter;
+
+impl Selector for CorrelationFilter {
+    fn select(
+        table: &DataFrame<Numeric>,
+        label_index: usize,
+        _training: &TrainingConfigs,
+        parameters: &Option<HashMap<String, Numeric>>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let threshold = parameters
+            .as_ref()
+            .and_then(|parameters| parameters.get("threshold").copied())
+            .unwrap_or(0.95);
+
+        // Center every feature column once so each correlation is just a dot product
+        let mut centered = Vec::new();
+        for idx in super::feature_indexes(table, label_index) {
+            let column = table
+                .get_column_idx(idx)
+                .ok_or("Could not find column in table!")?;
+            let count = column.values().count();
+            if count == 0 {
+                return Err("Number of items in the column is zero!".into());
+            }
+            let mean = column.values().sum::<Numeric>() / count as f64;
+            let values = column.values().map(|v| v - mean).collect::<Vec<Numeric>>();
+            let norm = values.iter().fold(0.0, |acc, v| acc + v * v).sqrt();
+            centered.push((idx, values, norm));
+        }
+
+        let mut selected: Vec<(usize, &Vec<Numeric>, f64)> = Vec::new();
+        for (idx, values, norm) in centered.iter() {
+            // Compare against the columns that were already kept
+            let redundant = selected.iter().find_map(|&(kept_idx, kept_values, kept_norm)| {
+                if *norm == 0.0 || kept_norm == 0.0 {
+                    // Correlation is not defined for constant columns
+                    return None;
+                }
+                let correlation = values
+                    .iter()
+                    .zip(kept_values)
+                    .fold(0.0, |acc, (x, y)| acc + x * y)
+                    / (norm * kept_norm);
+                if correlation.abs() > threshold {
+                    Some((kept_idx, correlation))
+                } else {
+                    None
+                }
+            });
+
+            match redundant {
+                Some((kept_idx, correlation)) => println!(
+                    "{} is correlated with {} (r = {})",
+                    super::column_name(table, *idx),
+                    super::column_name(table, kept_idx),
+                    correlation
+                ),
+                None => selected.push((*idx, values, *norm)),
+            }
+        }
+
+        Ok(selected.into_iter().map(|(idx, _, _)| idx).collect())
+    }
+}
@@ -0,0 +1,110 @@
+//! This module contains the logic to select which feature columns of the table are used to train
+//! a model. Columns that are not selected are removed from the table.
+
+mod correlation;
+mod mutual_information;
+mod stepwise;
+mod variance;
+
+use crate::config::{SelectionStageConfigs, TrainingConfigs};
+
+use crate::data::data_frame::DataFrame;
+use crate::types::Numeric;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+pub trait Selector {
+    fn select(
+        table: &DataFrame<Numeric>,
+        label_index: usize,
+        training: &TrainingConfigs,
+        parameters: &Option<HashMap<String, Numeric>>,
+    ) -> Result<Vec<usize>, Box<dyn Error>>;
+}
+
+type SelectFnPtr = fn(&DataFrame<Numeric>, usize, &TrainingConfigs, &Option<HashMap<String, Numeric>>) -> Result<Vec<usize>, Box<dyn Error>>;
+
+pub fn get_selector(name: &str) -> Result<SelectFnPtr, Box<dyn Error>> {
+    match name {
+        "variance-threshold" => Ok(variance::VarianceThreshold::select),
+        "correlation-filter" => Ok(correlation::CorrelationFilter::select),
+        "mutual-information" => Ok(mutual_information::MutualInformation::select),
+        "forward-selection" => Ok(stepwise::ForwardSelection::select),
+        "backward-elimination" => Ok(stepwise::BackwardElimination::select),
+        _ => Err(format!("Invalid selector name given: {}", name).into()),
+    }
+}
+
+/// Supervised selectors use the label to score features (mutual information) or train the model to
+/// score feature subsets (wrappers), so they must only see training rows and run inside each
+/// training fold instead of on the whole table
+pub fn is_supervised(name: &str) -> bool {
+    matches!(
+        name,
+        "mutual-information" | "forward-selection" | "backward-elimination"
+    )
+}
+
+/// Returns a printable name for a column, columns without headers are named by their index
+pub fn column_name(table: &DataFrame<Numeric>, idx: usize) -> String {
+    table
+        .get_column_idx(idx)
+        .and_then(|column| column.get_name())
+        .map(|name| name.to_owned())
+        .unwrap_or_else(|| format!("x[{}]", idx))
+}
+
+/// Runs every selector in order and removes the feature columns that were not selected. Returns the
+/// index of the label column in the reduced table and the indexes, in the original table, of the
+/// columns that were kept (label included).
+pub fn apply(
+    table: &mut DataFrame<Numeric>,
+    training: &TrainingConfigs,
+    parameters: &[SelectionStageConfigs],
+) -> Result<(usize, Vec<usize>), Box<dyn Error>> {
+    let label_index = training.label_index;
+    if table.get_column_idx(label_index).is_none() {
+        return Err("Couldn't find index of column of target value!".into());
+    }
+
+    // Keep the names of the original columns so removed and surviving columns can be reported
+    // even when the table has no headers
+    let mut names = (0..table.columns().len())
+        .map(|idx| column_name(table, idx))
+        .collect::<Vec<String>>();
+    let mut kept = (0..table.columns().len()).collect::<Vec<usize>>();
+
+    let mut label_index = label_index;
+    for parameter in parameters.iter() {
+        let select = get_selector(parameter.name.as_str())?;
+        let selected = select(table, label_index, training, &parameter.parameters)?;
+
+        // Remove columns from the back so the indexes of the remaining columns don't shift
+        let num_columns = table.columns().len();
+        for idx in (0..num_columns).rev() {
+            if idx == label_index || selected.contains(&idx) {
+                continue;
+            }
+            println!("{} removed column: {}", parameter.name, names.remove(idx));
+            kept.remove(idx);
+            table.remove_column(idx);
+            if idx < label_index {
+                label_index -= 1;
+            }
+        }
+    }
+
+    // Report the columns that survived every selector
+    names.remove(label_index);
+    println!("Selected features: {:?}", names);
+
+    Ok((label_index, kept))
+}
+
+/// Returns the indexes of all the columns in the table except the label column
+fn feature_indexes(table: &DataFrame<Numeric>, label_index: usize) -> Vec<usize> {
+    (0..table.columns().len())
+        .filter(|&idx| idx != label_index)
+        .collect()
+}
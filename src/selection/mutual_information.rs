@@ -0,0 +1,110 @@
+// mutual_information.rs
+
+//! This selector keeps the k feature columns that share the most mutual information with the label
+//! column. Continuous values are discretized into equal-width bins before the joint histogram is
+//! calculated, columns with metadata (ordinal/nominal) and columns with few unique values are used
+//! as they are. The label makes this a supervised selector, it is run by the training stage on
+//! the training rows of every fold.
+
+use super::Selector;
+use crate::config::TrainingConfigs;
+use crate::data::column::Column;
+use crate::data::data_frame::DataFrame;
+use crate::types::{Numeric, NUMERIC_DIGIT_PRECISION};
+
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct MutualInformation;
+
+impl Selector for MutualInformation {
+    fn select(
+        table: &DataFrame<Numeric>,
+        label_index: usize,
+        _training: &TrainingConfigs,
+        parameters: &Option<HashMap<String, Numeric>>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let parameters = parameters.as_ref().ok_or("No parameters given!")?;
+        let num_features = *parameters
+            .get("num_features")
+            .ok_or("num_features parameter not present!")? as usize;
+        let num_bins = parameters.get("num_bins").copied().unwrap_or(10.0) as usize;
+
+        if num_bins < 2 {
+            return Err("Number of bins is less than 2!".into());
+        }
+
+        let label_column = table
+            .get_column_idx(label_index)
+            .ok_or("Couldn't find index of column of target value!")?;
+        let labels = discretize(label_column, num_bins);
+
+        // Score every feature column by the mutual information with the label
+        let mut scores = Vec::new();
+        for idx in super::feature_indexes(table, label_index) {
+            let column = table
+                .get_column_idx(idx)
+                .ok_or("Could not find column in table!")?;
+            let score = mutual_information(&discretize(column, num_bins), &labels);
+            println!("{}: mutual information {}", super::column_name(table, idx), score);
+            scores.push((idx, score));
+        }
+
+        // Keep the k best scoring features
+        scores.sort_by(|(_, x), (_, y)| y.partial_cmp(x).unwrap());
+        Ok(scores
+            .into_iter()
+            .take(num_features)
+            .map(|(idx, _)| idx)
+            .collect())
+    }
+}
+
+/// Maps the values in the column into discrete symbols
+fn discretize(column: &Column<Numeric>, num_bins: usize) -> Vec<i64> {
+    let keys = column
+        .values()
+        .map(|value| (value / NUMERIC_DIGIT_PRECISION) as i64)
+        .collect::<Vec<i64>>();
+
+    // Categorical columns and columns with few distinct values are already discrete
+    let mut unique = keys.clone();
+    unique.sort();
+    unique.dedup();
+    if column.get_metadata().is_some() || unique.len() <= num_bins {
+        return keys;
+    }
+
+    // Place the values in equal-width bins
+    let smallest = column.values().copied().fold(Numeric::INFINITY, Numeric::min);
+    let biggest = column.values().copied().fold(Numeric::NEG_INFINITY, Numeric::max);
+    let bin_range = (biggest - smallest) / num_bins as f64;
+    column
+        .values()
+        .map(|value| (((value - smallest) / bin_range) as i64).min(num_bins as i64 - 1))
+        .collect()
+}
+
+/// Calculates the mutual information (in nats) between two discrete variables
+fn mutual_information(x: &[i64], y: &[i64]) -> f64 {
+    let num_samples = x.len() as f64;
+
+    // Calculate the marginal and joint histograms
+    let mut x_count = HashMap::new();
+    let mut y_count = HashMap::new();
+    let mut joint_count = HashMap::new();
+    for (&x, &y) in x.iter().zip(y.iter()) {
+        *x_count.entry(x).or_insert(0) += 1;
+        *y_count.entry(y).or_insert(0) += 1;
+        *joint_count.entry((x, y)).or_insert(0) += 1;
+    }
+
+    joint_count
+        .iter()
+        .fold(0.0, |acc, ((x, y), &count)| {
+            let p_xy = count as f64 / num_samples;
+            let p_x = x_count[x] as f64 / num_samples;
+            let p_y = y_count[y] as f64 / num_samples;
+            acc + p_xy * (p_xy / (p_x * p_y)).ln()
+        })
+}
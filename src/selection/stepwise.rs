@@ -0,0 +1,218 @@
+// stepwise.rs
+
+//! This file implements greedy wrapper selectors. Feature subsets are scored by training the model
+//! given in the training configuration and measuring its error with the configured evaluator under
+//! an inner cross-validation. Forward selection starts with no features and adds the feature that
+//! improves the score the most at each step, backward elimination starts with all the features and
+//! removes the feature whose removal improves the score the most. The search stops when no step
+//! improves the score or when the requested number of features is reached. These selectors are run
+//! by the training stage on the training rows of every fold, so the rows a model is validated on
+//! never help choose its features.
+
+use super::Selector;
+use crate::config::TrainingConfigs;
+use crate::data::column::ColumnInfo;
+use crate::data::data_frame::DataFrame;
+use crate::types::Numeric;
+
+use crate::evaluation;
+use crate::models;
+use crate::trainers;
+use crate::validation;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct ForwardSelection;
+
+impl Selector for ForwardSelection {
+    fn select(
+        table: &DataFrame<Numeric>,
+        label_index: usize,
+        training: &TrainingConfigs,
+        parameters: &Option<HashMap<String, Numeric>>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        stepwise_search(table, label_index, training, parameters, true)
+    }
+}
+
+pub struct BackwardElimination;
+
+impl Selector for BackwardElimination {
+    fn select(
+        table: &DataFrame<Numeric>,
+        label_index: usize,
+        training: &TrainingConfigs,
+        parameters: &Option<HashMap<String, Numeric>>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        stepwise_search(table, label_index, training, parameters, false)
+    }
+}
+
+fn stepwise_search(
+    table: &DataFrame<Numeric>,
+    label_index: usize,
+    training: &TrainingConfigs,
+    parameters: &Option<HashMap<String, Numeric>>,
+    forward: bool,
+) -> Result<Vec<usize>, Box<dyn Error>> {
+    let features = super::feature_indexes(table, label_index);
+    let tolerance = parameters
+        .as_ref()
+        .and_then(|parameters| parameters.get("tolerance").copied())
+        .unwrap_or(0.0);
+    let num_features = parameters
+        .as_ref()
+        .and_then(|parameters| parameters.get("num_features").copied())
+        .map(|num_features| num_features as usize);
+
+    // Generate the inner cross-validation folds once so every subset is scored on the same data
+    let mut partitioning_parameters = training.partitioning.parameters.clone();
+    if let Some(num_folds) = parameters
+        .as_ref()
+        .and_then(|parameters| parameters.get("num_folds"))
+    {
+        partitioning_parameters.insert("num_folds".to_string(), *num_folds);
+    }
+    let partition = validation::get_partitioner(&training.partitioning.strategy)?;
+    let folds = partition(table, label_index, &partitioning_parameters).map_err(|e| {
+        format!("Couldn't split the training rows into the inner cross-validation folds: {}", e)
+    })?;
+
+    let num_samples = table
+        .get_column_idx(label_index)
+        .ok_or("Couldn't find index of column of target value!")?
+        .values()
+        .len();
+    let mut rows = Vec::new();
+    for idx in 0..num_samples {
+        rows.push(table.get_row(idx)?);
+    }
+    let columns = table.columns_info();
+
+    let mut selected = if forward { Vec::new() } else { features.clone() };
+    let mut best_score = if forward {
+        Numeric::INFINITY
+    } else {
+        score_subset(&rows, &columns, label_index, &selected, &folds, training)?
+    };
+    println!("step 0: {:?} -> score: {}", names(table, &selected), best_score);
+
+    let mut step = 1;
+    loop {
+        // Stop if the requested number of features was reached
+        if num_features.is_some_and(|num_features| selected.len() == num_features) {
+            break;
+        }
+
+        // Candidate subsets are made by adding (or removing) a single feature
+        let candidates = if forward {
+            features
+                .iter()
+                .filter(|feature| !selected.contains(feature))
+                .map(|&feature| [selected.as_slice(), &[feature]].concat())
+                .collect::<Vec<Vec<usize>>>()
+        } else if selected.len() > 1 {
+            selected
+                .iter()
+                .map(|&feature| {
+                    selected
+                        .iter()
+                        .filter(|&&other| other != feature)
+                        .copied()
+                        .collect()
+                })
+                .collect::<Vec<Vec<usize>>>()
+        } else {
+            Vec::new()
+        };
+
+        // Score every candidate and keep the best one
+        let mut best_candidate = None;
+        for candidate in candidates {
+            let score = score_subset(&rows, &columns, label_index, &candidate, &folds, training)?;
+            println!("step {}: trying {:?} -> score: {}", step, names(table, &candidate), score);
+            if best_candidate
+                .as_ref()
+                .is_none_or(|(_, best)| score < *best)
+            {
+                best_candidate = Some((candidate, score));
+            }
+        }
+
+        // Stop if no candidate improves the score
+        match best_candidate {
+            Some((candidate, score)) if score < best_score - tolerance => {
+                selected = candidate;
+                best_score = score;
+                println!("step {}: {:?} -> score: {}", step, names(table, &selected), best_score);
+            }
+            _ => break,
+        }
+        step += 1;
+    }
+
+    selected.sort();
+    Ok(selected)
+}
+
+/// Averages the evaluation metric of models trained only on the given features over every fold
+fn score_subset(
+    rows: &[Vec<Numeric>],
+    columns: &[ColumnInfo],
+    label_index: usize,
+    features: &[usize],
+    folds: &[(Vec<usize>, Vec<usize>)],
+    training: &TrainingConfigs,
+) -> Result<f64, Box<dyn Error>> {
+    let evaluate = evaluation::get_evaluator(&training.evaluation)?;
+    let mut model_builder = models::get_model_builder(&training.model.name)?;
+    if let Some(parameters) = training.model.parameters.as_ref() {
+        model_builder.with_hyperparameters(parameters)?;
+    }
+
+    // Build the rows with the subset of features, the label goes in the last position
+    let context = models::TrainingContext {
+        columns: features
+            .iter()
+            .chain(std::iter::once(&label_index))
+            .map(|&feature| columns[feature].clone())
+            .collect(),
+        partitioning: Some(training.partitioning.clone()),
+        task: Some(training.model.task.clone()),
+    };
+    model_builder.with_context(&context)?;
+    let project = |idx: &usize| -> Box<[Numeric]> {
+        features
+            .iter()
+            .chain(std::iter::once(&label_index))
+            .map(|&feature| rows[*idx][feature])
+            .collect()
+    };
+    let projected_label_index = features.len();
+
+    let mut fold_scores = Vec::new();
+    for (training_indexes, validation_indexes) in folds.iter() {
+        let training_set = training_indexes.iter().map(project).collect::<Vec<_>>();
+        let validation_set = validation_indexes.iter().map(project).collect::<Vec<_>>();
+
+        let model = model_builder.build(&training_set, projected_label_index, None)?;
+
+        fold_scores.push(trainers::evaluate_model(
+            model.as_ref(),
+            &validation_set,
+            projected_label_index,
+            &training.model.task,
+            &evaluate,
+        )?);
+    }
+
+    Ok(fold_scores.iter().sum::<f64>() / fold_scores.len() as f64)
+}
+
+fn names(table: &DataFrame<Numeric>, features: &[usize]) -> Vec<String> {
+    features
+        .iter()
+        .map(|&idx| super::column_name(table, idx))
+        .collect()
+}
@@ -0,0 +1,53 @@
+// variance.rs
+
+//! This selector removes feature columns whose sample variance is below a threshold. Columns with
+//! (near) constant values carry no information to tell samples apart.
+
+use super::Selector;
+use crate::config::TrainingConfigs;
+use crate::data::data_frame::DataFrame;
+use crate::types::Numeric;
+
+use std::collections::HashMap;
+use std::error::Error;
+
+pub struct VarianceThreshold;
+
+impl Selector for VarianceThreshold {
+    fn select(
+        table: &DataFrame<Numeric>,
+        label_index: usize,
+        _training: &TrainingConfigs,
+        parameters: &Option<HashMap<String, Numeric>>,
+    ) -> Result<Vec<usize>, Box<dyn Error>> {
+        let threshold = parameters
+            .as_ref()
+            .and_then(|parameters| parameters.get("threshold").copied())
+            .unwrap_or(0.0);
+
+        let mut selected = Vec::new();
+        for idx in super::feature_indexes(table, label_index) {
+            let column = table
+                .get_column_idx(idx)
+                .ok_or("Could not find column in table!")?;
+            let count = column.values().count();
+            if count < 2 {
+                return Err("Not enough values to calculate the variance!".into());
+            }
+
+            // Calculate the sample variance of the column
+            let mean = column.values().sum::<Numeric>() / count as f64;
+            let variance = column
+                .values()
+                .fold(0.0, |acc, &v| acc + (v - mean) * (v - mean))
+                / (count - 1) as f64;
+
+            println!("{}: variance {}", super::column_name(table, idx), variance);
+            if variance > threshold {
+                selected.push(idx);
+            }
+        }
+
+        Ok(selected)
+    }
+}
@@ -0,0 +1,347 @@
+// serve.rs
+
+//! This module serves a saved model over HTTP on localhost, so other services can score single
+//! rows without running the command line. Requests are handled one at a time:
+//!
+//! `GET /health` reports that the server is up and which model it serves.
+//!
+//! `POST /predict` takes a JSON body like `{"features": ["5.1", "3.5", "1.4", "0.2"]}` holding the
+//! raw values of one row, in the column order of the training input without the label column.
+//! The values go through the same parse, scrub, transform and selection steps as the `predict`
+//! command, and the response holds the prediction, plus the class probabilities for
+//! classification models.
+
+use crate::models::Model;
+use crate::persistence::{self, SavedPipeline};
+use crate::predict;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+// Largest request body accepted, a single row is far smaller
+const MAX_BODY_SIZE: usize = 1 << 20;
+// Requests are handled one at a time, a client that stalls is dropped after this long
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct PredictRequest {
+    // Strings are raw values, numbers are taken as written and nulls are missing values
+    features: Vec<Value>,
+}
+
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+pub fn serve(model_path: &str, port: u16) -> Result<(), Box<dyn Error>> {
+    let saved = persistence::load(model_path)?;
+    let model = saved.model()?;
+    println!("Loaded {} model from {}", saved.model_name, model_path);
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Serving on http://{}", listener.local_addr()?);
+
+    for stream in listener.incoming() {
+        // A broken connection only affects its own request
+        if let Err(e) = stream
+            .map_err(|e| e.into())
+            .and_then(|stream| handle_connection(stream, &saved, model.as_ref()))
+        {
+            println!("Connection error: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(
+    stream: TcpStream,
+    saved: &SavedPipeline,
+    model: &dyn Model,
+) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    // Request line, then headers up to an empty line
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    // Query strings aren't used by any endpoint
+    let target = parts.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default().to_string();
+
+    let mut content_length = Ok(0);
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid Content-Length header: {}", value.trim()));
+            }
+        }
+    }
+
+    let response = match content_length {
+        Err(message) => Response::error(400, &message),
+        Ok(length) if length > MAX_BODY_SIZE => {
+            Response::error(413, "The request body is too large")
+        }
+        Ok(length) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            route(&method, &path, &body, saved, model)
+        }
+    };
+    println!("{} {} -> {}", method, path, response.status);
+
+    write_response(stream, &response)
+}
+
+fn route(
+    method: &str,
+    path: &str,
+    body: &[u8],
+    saved: &SavedPipeline,
+    model: &dyn Model,
+) -> Response {
+    match (method, path) {
+        ("GET", "/health") => Response::ok(json!({
+            "status": "ok",
+            "model": saved.model_name,
+            "task": saved.task,
+            "version": saved.version,
+        })),
+        ("POST", "/predict") => match serde_json::from_slice::<PredictRequest>(body) {
+            Ok(request) => predict_row(&request, saved, model),
+            Err(e) => Response::error(400, &format!("Invalid request body: {}", e)),
+        },
+        (_, "/health") | (_, "/predict") => Response::error(405, "Method not allowed"),
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+fn predict_row(request: &PredictRequest, saved: &SavedPipeline, model: &dyn Model) -> Response {
+    let expected = saved.parsing.len() - 1;
+    if request.features.len() != expected {
+        return Response::error(
+            400,
+            &format!(
+                "Expected {} feature values without the label, got {}",
+                expected,
+                request.features.len()
+            ),
+        );
+    }
+
+    // Treat the values like the input stage treats the fields of a file
+    let mut raw = Vec::new();
+    for value in request.features.iter() {
+        let entry = match value {
+            Value::String(value) => value.trim().to_string(),
+            Value::Number(value) => value.to_string(),
+            Value::Null => {
+                raw.push(None);
+                continue;
+            }
+            _ => return Response::error(400, "Feature values must be strings, numbers or null"),
+        };
+        if saved.missing_values.contains(&entry) {
+            raw.push(None);
+        } else {
+            raw.push(Some(entry));
+        }
+    }
+
+    let (sample, label_transforms) = match predict::prepare_row(saved, &raw) {
+        Ok(Some(prepared)) => prepared,
+        Ok(None) => {
+            return Response::error(422, "The row has missing values no scrubber fills");
+        }
+        Err(e) => return Response::error(400, &e.to_string()),
+    };
+
+    if saved.task == "classification" {
+        let probabilities = model
+            .predict_proba(&sample)
+            .into_iter()
+            .map(|(class, probability)| (predict::class_name(saved, class), json!(probability)))
+            .collect::<serde_json::Map<String, Value>>();
+        Response::ok(json!({
+            "prediction": predict::class_name(saved, model.label(&sample)),
+            "probabilities": probabilities,
+        }))
+    } else {
+        let prediction = predict::regression_value(model, &sample, &label_transforms);
+        Response::ok(json!({ "prediction": prediction }))
+    }
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> Result<(), Box<dyn Error>> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::data::column::ColumnInfo;
+    use crate::models::{self, SavedModel};
+    use crate::persistence::FORMAT_VERSION;
+    use crate::types::Numeric;
+
+    use std::collections::HashMap;
+    use std::thread;
+
+    // A null classifier trained on one numeric feature and a nominal label, two rows of "a" and
+    // one of "b", so it always predicts "a"
+    fn saved_pipeline() -> (SavedPipeline, Box<dyn Model>) {
+        let rows: Vec<Box<[Numeric]>> = vec![
+            vec![1.0, 0.0].into_boxed_slice(),
+            vec![2.0, 0.0].into_boxed_slice(),
+            vec![3.0, 1.0].into_boxed_slice(),
+        ];
+        let mut builder = models::get_model_builder("null-classifier").unwrap();
+        let model = builder.build(&rows, 1, None).unwrap();
+        let saved = SavedPipeline {
+            version: FORMAT_VERSION,
+            format: "csv".to_string(),
+            missing_values: vec!["?".to_string()],
+            headers: false,
+            parsing: vec!["numeric".to_string(), "nominal".to_string()],
+            columns: vec![
+                ColumnInfo::default(),
+                ColumnInfo {
+                    name: None,
+                    metadata: Some(HashMap::from([(0, "a".to_string()), (1, "b".to_string())])),
+                },
+            ],
+            scrub: Vec::new(),
+            transform: Vec::new(),
+            selection: None,
+            input_label_index: 1,
+            label_index: 1,
+            task: "classification".to_string(),
+            model_name: "null-classifier".to_string(),
+            hyperparameters: model.get_hyperparameters(),
+            model: SavedModel::new(model.as_ref()).unwrap(),
+        };
+        (saved, model)
+    }
+
+    #[test]
+    fn health_reports_the_model() {
+        let (saved, model) = saved_pipeline();
+        let response = route("GET", "/health", b"", &saved, model.as_ref());
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["status"], "ok");
+        assert_eq!(response.body["model"], "null-classifier");
+    }
+
+    #[test]
+    fn predict_returns_class_and_probabilities() {
+        let (saved, model) = saved_pipeline();
+        let body = br#"{"features": ["1.5"]}"#;
+        let response = route("POST", "/predict", body, &saved, model.as_ref());
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["prediction"], "a");
+        let probability = response.body["probabilities"]["b"].as_f64().unwrap();
+        assert!((probability - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn predict_rejects_wrong_feature_count() {
+        let (saved, model) = saved_pipeline();
+        let body = br#"{"features": [1.5, 2.5]}"#;
+        let response = route("POST", "/predict", body, &saved, model.as_ref());
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn unknown_path_is_not_found() {
+        let (saved, model) = saved_pipeline();
+        let response = route("GET", "/models", b"", &saved, model.as_ref());
+        assert_eq!(response.status, 404);
+    }
+
+    // Serves a single connection on a free port and returns the raw response to the request
+    fn send(request: &str) -> String {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (saved, model) = saved_pipeline();
+            let (stream, _) = listener.accept().unwrap();
+            handle_connection(stream, &saved, model.as_ref()).unwrap();
+        });
+
+        let mut client = TcpStream::connect(address).unwrap();
+        client.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        server.join().unwrap();
+        response
+    }
+
+    #[test]
+    fn serves_predictions_over_http() {
+        let body = r#"{"features": [2.5]}"#;
+        let response = send(&format!(
+            "POST /predict HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(r#""prediction":"a""#));
+    }
+
+    #[test]
+    fn malformed_content_length_is_a_bad_request() {
+        let response = send("POST /predict HTTP/1.1\r\nContent-Length: ten\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+}
@@ -4,12 +4,14 @@
 //! builds a model, trains and evaluates the model for each partition
 //! using the kx2 cross-validation algorithm.
 
-use crate::config::ConfigStruct;
+use crate::config::{SelectionStageConfigs, TrainingConfigs};
 use crate::data::data_frame::DataFrame;
 use crate::types::Numeric;
 
 use crate::evaluation;
+use crate::evaluation::EvaluationStrategy;
 use crate::models;
+use crate::models::{ModelBuilder, TrainingContext};
 use crate::tuning;
 use crate::validation;
 
@@ -18,6 +20,8 @@ use plotly::layout::ShapeLine;
 use rand::prelude::*;
 use rand::seq::SliceRandom;
 
+use super::TrainedModel;
+
 use std::collections::HashMap;
 use std::error::Error;
 
@@ -30,20 +34,24 @@ const MAKE_PLOTS: bool = true;
 
 pub fn train_and_evaluate(
     df: &DataFrame<Numeric>,
-    configs: &ConfigStruct,
-) -> Result<f64, Box<dyn Error>> {
+    training: &TrainingConfigs,
+    supervised: &[SelectionStageConfigs],
+) -> Result<(f64, TrainedModel), Box<dyn Error>> {
     // Create a training data partitioner for cross-correlation validaton
-    let partition = validation::get_partitioner(&configs.training.partitioning.strategy)?;
+    let partition = validation::get_partitioner(&training.partitioning.strategy)?;
 
     // Fetch evaluator specified on configuration file
-    let evaluate = evaluation::get_evaluator(&configs.training.evaluation)?;
+    let evaluate = evaluation::get_evaluator(&training.evaluation)?;
 
     // Fetch the model specified on configuration file
-    let mut model_builder = models::get_model_builder(&configs.training.model.name)?;
+    let mut model_builder = models::get_model_builder(&training.model.name)?;
+    let context = models::TrainingContext::new(df)
+        .with_partitioning(&training.partitioning)
+        .with_task(&training.model.task);
 
     // Split the training data into training and validation set
     let first_fold_config = HashMap::from([("num_folds".to_string(), 5.0)]);
-    let first_folds = partition(df, configs.training.label_index, &first_fold_config)?;
+    let first_folds = partition(df, training.label_index, &first_fold_config)?;
 
     // Choose a random 80-20 split of the original data frame
     let (training_indexes, validation_indexes) =
@@ -69,20 +77,27 @@ pub fn train_and_evaluate(
 
     let mut first_set = Vec::new();
     let mut second_set = Vec::new();
-    let mut model1_predictions = Vec::new();
-    let mut model2_predictions = Vec::new();
     let mut models = Vec::new();
     let folding_config = HashMap::from([("num_folds".to_string(), 2.0)]);
 
-    let hyperparameter_combinations =
+    let mut hyperparameter_combinations =
         tuning::grid_search_tuning::get_hyperparameter_combinations(
-            &configs.training.model.tuning,
+            &training.model.tuning,
         )?;
 
+    // Fixed model parameters are used by every combination, tuned values take precedence
+    if let Some(parameters) = training.model.parameters.as_ref() {
+        for combination in hyperparameter_combinations.iter_mut() {
+            for (key, value) in parameters.iter() {
+                combination.entry(key.clone()).or_insert(value.clone());
+            }
+        }
+    }
+
     for _ in 0..5 {
         let folds = partition(
             &training_and_testing_df,
-            configs.training.label_index,
+            training.label_index,
             &folding_config,
         )?;
 
@@ -120,50 +135,29 @@ pub fn train_and_evaluate(
             &tuning_hyperparameter_2
         );
 
+        // Build the models and evaluate them on the validation set
         println!("BUILDING MODEL 1");
         model_builder.with_hyperparameters(&tuning_hyperparameter_1)?;
-        let model1 = model_builder.build(&first_set, configs.training.label_index)?;
-
-        println!("BUILDING MODEL 2");
-        model_builder.with_hyperparameters(&tuning_hyperparameter_2)?;
-        let model2 = model_builder.build(&second_set, configs.training.label_index)?;
-
-        // Generate predictions for the first model
-        println!("MODEL 1 PREDICTIONS");
-        model1_predictions.clear();
-        for sample in validation_set.iter() {
-            let res = match configs.training.model.task.as_str() {
-                "regression" => Ok(model1.predict(sample)),
-                "classification" => Ok(model1.label(sample)),
-                _ => Err("Invalid model task, only regression and classification are supported"),
-            }?;
-            model1_predictions.push(res);
-        }
-
-        // Generate predictions for the second model
-        println!("MODEL 2 PREDICTIONS");
-        model2_predictions.clear();
-        for sample in validation_set.iter() {
-            let res = match configs.training.model.task.as_str() {
-                "regression" => Ok(model2.predict(sample)),
-                "classification" => Ok(model2.label(sample)),
-                _ => Err("Invalid model task, only regression and classification are supported"),
-            }?;
-            model2_predictions.push(res);
-        }
-
-        // Evaluate the first model
-        let model1_error_metric = evaluate(
-            &model1_predictions,
+        let (model1, model1_error_metric) = train_and_test(
+            &mut model_builder,
+            &context,
+            &first_set,
             &validation_set,
-            configs.training.label_index,
+            training,
+            supervised,
+            &evaluate,
         )?;
 
-        // Evaluate the second model
-        let model2_error_metric = evaluate(
-            &model2_predictions,
+        println!("BUILDING MODEL 2");
+        model_builder.with_hyperparameters(&tuning_hyperparameter_2)?;
+        let (model2, model2_error_metric) = train_and_test(
+            &mut model_builder,
+            &context,
+            &second_set,
             &validation_set,
-            configs.training.label_index,
+            training,
+            supervised,
+            &evaluate,
         )?;
 
         // Push model snapshots
@@ -176,14 +170,18 @@ pub fn train_and_evaluate(
         println!(
             "error_metric: {}\nhyper-parameters:\n{:#?}",
             error_metric,
-            model.get_hyperparameters()
+            model.model.get_hyperparameters()
         );
     }
 
     // Choose the model with best performance
-    let (best_model, best_performance) = models
+    let best_idx = models
         .iter()
-        .min_by(|(_, perf_1), (_, perf_2)| perf_1.abs().partial_cmp(&perf_2.abs()).unwrap())
+        .enumerate()
+        .min_by(|(_, (_, perf_1)), (_, (_, perf_2))| {
+            perf_1.abs().partial_cmp(&perf_2.abs()).unwrap()
+        })
+        .map(|(idx, _)| idx)
         .expect("No best model found!");
 
     // Get the average performance of the model parameter tunning
@@ -192,15 +190,17 @@ pub fn train_and_evaluate(
         .fold(0.0, |acc, (_, model_error_metric)| acc + model_error_metric)
         / models.len() as f64;
 
+    // Keep the best model, it is the one the experiment returns
+    let (best_model, best_performance) = models.swap_remove(best_idx);
+
     println!("Best model performance: {:?}", best_performance);
     println!(
         "Best model hyper-parameters:\n{:#?}",
-        best_model.get_hyperparameters()
+        best_model.model.get_hyperparameters()
     );
     println!("Average model performance: {:?}", avg_model_error_metric);
 
-    let best_hyperparameters = best_model.get_hyperparameters();
-    let mut model_predictions = Vec::new();
+    let best_hyperparameters = best_model.model.get_hyperparameters();
     let mut model_error_metrics = Vec::new();
     let mut training_set = Vec::new();
     let mut testing_set = Vec::new();
@@ -208,7 +208,7 @@ pub fn train_and_evaluate(
         // This generates two folds
         let folds = partition(
             &training_and_testing_df,
-            configs.training.label_index,
+            training.label_index,
             &folding_config,
         )?;
 
@@ -226,33 +226,21 @@ pub fn train_and_evaluate(
                 testing_set.push(training_and_testing_df.get_row(idx)?.into_boxed_slice());
             }
 
-            // Create model instance
+            // Create model instance and evaluate it on the testing set
             model_builder.with_hyperparameters(&best_hyperparameters)?;
-            let model = model_builder.build(&training_set, configs.training.label_index)?;
-
-            // Generate predictions for the model
-            model_predictions.clear();
-            for sample in testing_set.iter() {
-                let res = match configs.training.model.task.as_str() {
-                    "regression" => Ok(model.predict(sample)),
-                    "classification" => Ok(model.label(sample)),
-                    _ => {
-                        Err("Invalid model task, only regression and classification are supported")
-                    }
-                }?;
-                model_predictions.push(res);
-            }
-
-            // Evaluate the model
-            let model_error_metric = evaluate(
-                &model_predictions,
+            let (model, model_error_metric) = train_and_test(
+                &mut model_builder,
+                &context,
+                &training_set,
                 &testing_set,
-                configs.training.label_index,
+                training,
+                supervised,
+                &evaluate,
             )?;
 
             // Push model error metrics
             model_error_metrics.push(model_error_metric);
-            println!("model metrics:\n{:#?}", model.get_hyperparameters());
+            println!("model metrics:\n{:#?}", model.model.get_hyperparameters());
         }
     }
 
@@ -270,7 +258,43 @@ pub fn train_and_evaluate(
         );
     }
 
-    Ok(average_error)
+    Ok((average_error, best_model))
+}
+
+/// Trains a model on the training set, using the features the supervised selectors choose on that set
+/// alone, and returns it with its evaluation metric on the testing set
+fn train_and_test(
+    model_builder: &mut Box<dyn ModelBuilder>,
+    context: &TrainingContext,
+    training_set: &[Box<[Numeric]>],
+    testing_set: &[Box<[Numeric]>],
+    training: &TrainingConfigs,
+    supervised: &[SelectionStageConfigs],
+    evaluate: &EvaluationStrategy,
+) -> Result<(TrainedModel, f64), Box<dyn Error>> {
+    let features = super::select_features(&context.columns, training_set, training, supervised)?;
+    let (training_set, label_index, model_context) = super::with_features(
+        training_set.to_vec(),
+        training.label_index,
+        context,
+        features.as_ref(),
+    );
+    let testing_set = match features.as_ref() {
+        Some(features) => features.project(testing_set),
+        None => testing_set.to_vec(),
+    };
+
+    model_builder.with_context(&model_context)?;
+    let model = model_builder.build(&training_set, label_index, None)?;
+    let error_metric = super::evaluate_model(
+        model.as_ref(),
+        &testing_set,
+        label_index,
+        &training.model.task,
+        evaluate,
+    )?;
+
+    Ok((TrainedModel { model, features }, error_metric))
 }
 
 fn make_lollipop(data: Vec<f64>, title: &str, filename: &str) {
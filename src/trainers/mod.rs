@@ -5,20 +5,131 @@
 mod simple;
 mod kx2_folds;
 
+use crate::data::column::ColumnInfo;
 use crate::data::data_frame::DataFrame;
-use crate::config::ConfigStruct;
+use crate::config::{SelectionStageConfigs, TrainingConfigs};
+use crate::evaluation::EvaluationStrategy;
+use crate::models::{Model, TrainingContext};
+use crate::selection;
 use crate::types::Numeric;
 
 use std::error::Error;
 
+// Training strategies return the validation performance and the best model they trained. Wrapper
+// selectors are given to the strategies so they run on the training rows of every fold.
 pub trait TrainingDirector {
-    fn train_and_evaluate(df: &DataFrame<Numeric>, configs: &ConfigStruct) -> Result<f64, Box<dyn Error>>;
+    fn train_and_evaluate(
+        df: &DataFrame<Numeric>,
+        training: &TrainingConfigs,
+        supervised: &[SelectionStageConfigs],
+    ) -> Result<(f64, TrainedModel), Box<dyn Error>>;
 }
 
-pub fn train_and_evaluate(df: &DataFrame<Numeric>, configs: &ConfigStruct) -> Result<f64, Box<dyn Error>> {
-    match configs.training.strategy.as_str() {
-        "simple" => simple::train_and_evaluate(df, configs),
-        "kx2-folds" => kx2_folds::train_and_evaluate(df, configs),
+pub fn train_and_evaluate(
+    df: &DataFrame<Numeric>,
+    training: &TrainingConfigs,
+    supervised: &[SelectionStageConfigs],
+) -> Result<(f64, TrainedModel), Box<dyn Error>> {
+    match training.strategy.as_str() {
+        "simple" => simple::train_and_evaluate(df, training, supervised),
+        "kx2-folds" => kx2_folds::train_and_evaluate(df, training, supervised),
         _ => Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Unknown strategy")))
     }
 }
+
+/// A trained model, with the columns it was trained on when supervised selectors chose its features
+pub struct TrainedModel {
+    pub model: Box<dyn Model>,
+    pub features: Option<FeatureSubset>,
+}
+
+/// Columns of the table kept by the supervised selectors for one training set, label included
+#[derive(Debug, Clone)]
+pub struct FeatureSubset {
+    pub columns: Vec<usize>,
+    // Index of the label column among the kept columns
+    pub label_index: usize,
+}
+
+impl FeatureSubset {
+    /// Reduces the rows to the kept columns
+    pub fn project(&self, rows: &[Box<[Numeric]>]) -> Vec<Box<[Numeric]>> {
+        rows.iter()
+            .map(|row| self.columns.iter().map(|&column| row[column]).collect())
+            .collect()
+    }
+}
+
+/// Runs the supervised selectors on the training rows only, so the rows a model is evaluated on
+/// never help choose its features. Returns None when there are no supervised selectors.
+pub fn select_features(
+    columns: &[ColumnInfo],
+    training_set: &[Box<[Numeric]>],
+    training: &TrainingConfigs,
+    supervised: &[SelectionStageConfigs],
+) -> Result<Option<FeatureSubset>, Box<dyn Error>> {
+    if supervised.is_empty() {
+        return Ok(None);
+    }
+    let mut table = DataFrame::from_rows(training_set.to_vec())?;
+    table.set_columns_info(columns);
+    let (label_index, columns) = selection::apply(&mut table, training, supervised)?;
+    Ok(Some(FeatureSubset {
+        columns,
+        label_index,
+    }))
+}
+
+/// Returns the training set, label index and model context to train with, reduced to the given
+/// features if any
+pub fn with_features(
+    training_set: Vec<Box<[Numeric]>>,
+    label_index: usize,
+    context: &TrainingContext,
+    features: Option<&FeatureSubset>,
+) -> (Vec<Box<[Numeric]>>, usize, TrainingContext) {
+    match features {
+        Some(features) => (
+            features.project(&training_set),
+            features.label_index,
+            context.select_columns(&features.columns),
+        ),
+        None => (training_set, label_index, context.clone()),
+    }
+}
+
+/// Evaluates a model on the given samples. Evaluators of predicted values get the model
+/// predictions (or labels for classification tasks), evaluators of class probabilities get the
+/// class probabilities predicted by the model.
+pub fn evaluate_model(
+    model: &dyn Model,
+    samples: &[Box<[Numeric]>],
+    label_index: usize,
+    task: &str,
+    evaluator: &EvaluationStrategy,
+) -> Result<f64, Box<dyn Error>> {
+    match evaluator {
+        EvaluationStrategy::Predictions(evaluate) => {
+            let mut predictions = Vec::new();
+            for sample in samples.iter() {
+                let res = match task {
+                    "regression" => Ok(model.predict(sample)),
+                    "classification" => Ok(model.label(sample)),
+                    _ => Err("Invalid model task, only regression and classification are supported"),
+                }?;
+                predictions.push(res);
+            }
+            evaluate(&predictions, samples, label_index)
+        }
+        EvaluationStrategy::Probabilities(evaluate) => {
+            if task != "classification" {
+                return Err("Class probability evaluators are only supported for classification".into());
+            }
+            let probabilities = samples
+                .iter()
+                .map(|sample| model.predict_proba(sample))
+                .collect::<Vec<_>>();
+            evaluate(&probabilities, samples, label_index)
+        }
+    }
+}
@@ -6,82 +6,106 @@
 
 use crate::data::data_frame::DataFrame;
 use crate::types::Numeric;
-use crate::config::ConfigStruct;
+use crate::config::{SelectionStageConfigs, TrainingConfigs};
 
 use crate::validation;
 use crate::evaluation;
 use crate::models;
 
+use super::TrainedModel;
+
 use std::error::Error;
 
-pub fn train_and_evaluate(df: &DataFrame<Numeric>, configs: &ConfigStruct) -> Result<f64, Box<dyn Error>>{
+pub fn train_and_evaluate(
+    df: &DataFrame<Numeric>,
+    training: &TrainingConfigs,
+    supervised: &[SelectionStageConfigs],
+) -> Result<(f64, TrainedModel), Box<dyn Error>> {
     // Create a training data partitioner for cross-correlation validaton
-    let partitioner = validation::get_partitioner(&configs.training.partitioning.strategy)?;
+    let partitioner = validation::get_partitioner(&training.partitioning.strategy)?;
     let folds = partitioner(
         df,
-        configs.training.label_index,
-        &configs.training.partitioning.parameters,
+        training.label_index,
+        &training.partitioning.parameters,
     )?;
 
     // Fetch evaluator specified on configuration file
-    let evaluator = evaluation::get_evaluator(&configs.training.evaluation)?;
+    let evaluator = evaluation::get_evaluator(&training.evaluation)?;
 
     // Fetch the model specified on configuration file
-    let mut model_builder = models::get_model_builder(&configs.training.model.name)?;
+    let mut model_builder = models::get_model_builder(&training.model.name)?;
+    let context = models::TrainingContext::new(df)
+        .with_partitioning(&training.partitioning)
+        .with_task(&training.model.task);
+    if let Some(parameters) = training.model.parameters.as_ref() {
+        model_builder.with_hyperparameters(parameters)?;
+    }
 
-    let mut model_output = Vec::new();
-    let mut validation_set = Vec::new();
-    let mut training_set = Vec::new();
     let mut validation_set_performance = Vec::new();
+    let mut best_model: Option<(TrainedModel, f64)> = None;
     for (fold_idx, (train_indices, validation_indices)) in folds.iter().enumerate() {
         println!("\nFOLD #: {}", fold_idx);
 
         // Create training data set
         println!("TRAINING");
-        training_set.clear();
+        let mut training_set = Vec::new();
         for &idx in train_indices {
             training_set.push(df.get_row(idx)?.into_boxed_slice());
         }
         println!("SIZE: {}", training_set.len());
 
+        // Choose the features using the training rows of the fold only
+        let features = super::select_features(&context.columns, &training_set, training, supervised)?;
+        let (training_set, label_index, fold_context) =
+            super::with_features(training_set, training.label_index, &context, features.as_ref());
+
         // Train model on training data set
-        let model = model_builder.build(&training_set, configs.training.label_index)?;
+        model_builder.with_context(&fold_context)?;
+        let model = model_builder.build(&training_set, label_index, None)?;
 
         // Use model to evaluate performance of training data
-        model_output.clear();
-        for sample in training_set.iter() {
-            model_output.push(model.predict(sample));
-        }
-
-        // Calculate performance
-        let training_performance =
-            evaluator(&model_output, &training_set, configs.training.label_index)?;
+        let training_performance = super::evaluate_model(
+            model.as_ref(),
+            &training_set,
+            label_index,
+            &training.model.task,
+            &evaluator,
+        )?;
         println!("ERROR: {}", training_performance);
 
         // Create validation data set
         println!("VALIDATION");
-        validation_set.clear();
+        let mut validation_set = Vec::new();
         for &idx in validation_indices {
             validation_set.push(df.get_row(idx)?.into_boxed_slice());
         }
-        println!("SIZE: {}", validation_set.len());
-
-        // Use model to predict labels on validation data
-        model_output.clear();
-        for sample in validation_set.iter() {
-            model_output.push(model.predict(sample));
+        if let Some(features) = features.as_ref() {
+            validation_set = features.project(&validation_set);
         }
+        println!("SIZE: {}", validation_set.len());
 
-        // Calculate validation performance
-        let validation_performance =
-            evaluator(&model_output, &validation_set, configs.training.label_index)?;
+        // Use model to predict labels on validation data and calculate validation performance
+        let validation_performance = super::evaluate_model(
+            model.as_ref(),
+            &validation_set,
+            label_index,
+            &training.model.task,
+            &evaluator,
+        )?;
         println!("ERROR: {}", validation_performance);
         validation_set_performance.push(validation_performance);
 
         println!("Model hyper-parameters:\n{:#?}", model.get_hyperparameters());
+
+        // Keep the model of the fold with the best validation performance
+        if best_model.as_ref().is_none_or(|(_, best)| validation_performance.abs() < best.abs()) {
+            best_model = Some((TrainedModel { model, features }, validation_performance));
+        }
     }
 
     let avg_validation_performance = validation_set_performance.iter().sum::<f64>() / validation_set_performance.len() as f64;
 
-    Ok(avg_validation_performance)
+    let (best_model, _) = best_model.ok_or("No folds to train on!")?;
+
+    Ok((avg_validation_performance, best_model))
 }
\ No newline at end of file
@@ -0,0 +1,104 @@
+// autoencoder.rs
+
+//! This module implements feature extraction with an autoencoder: the network is trained on the
+//! feature columns and the features are replaced by their bottleneck encoding. Unlike the other
+//! transforms it works on the whole table, the label column (if there is one) is left out of the
+//! training and kept as the last column of the new table. Numeric settings can be given as
+//! `parameters`, settings written as text (e.g. `activation: relu` or `hidden_layers: 8,4`) go in
+//! `hyperparameters`.
+
+use std::collections::HashMap;
+
+use crate::data::column::Column;
+use crate::data::data_frame::DataFrame;
+use crate::models::autoencoder::Autoencoder;
+use crate::models::mlp::Layer;
+use crate::types::Numeric;
+
+use std::error::Error;
+
+pub struct AutoencoderEncoding;
+
+impl AutoencoderEncoding {
+    /// Replaces the feature columns of the table by the encoding and returns the new index of the
+    /// label column with the encoder layers
+    pub fn apply(
+        table: &mut DataFrame<Numeric>,
+        label_index: Option<usize>,
+        parameters: &Option<HashMap<String, Numeric>>,
+        hyperparameters: &Option<HashMap<String, String>>,
+    ) -> Result<(Option<usize>, Vec<Layer>), Box<dyn Error>> {
+        if let Some(label_index) = label_index {
+            if table.get_column_idx(label_index).is_none() {
+                return Err("Couldn't find index of column of target value!".into());
+            }
+        }
+
+        // Numeric parameters are written as text like the other settings of the autoencoder
+        let mut settings = HashMap::new();
+        if let Some(parameters) = parameters.as_ref() {
+            for (key, value) in parameters.iter() {
+                if key == "activation" {
+                    return Err("The autoencoder activation is a name, give it in hyperparameters!"
+                        .into());
+                }
+                settings.insert(key.clone(), value.to_string());
+            }
+        }
+        if let Some(hyperparameters) = hyperparameters.as_ref() {
+            settings.extend(hyperparameters.clone());
+        }
+        let mut autoencoder = Autoencoder::new();
+        autoencoder.set_hyperparameters(&settings)?;
+
+        let num_rows = table.get_column_idx(0).map_or(0, |column| column.values().count());
+        let rows = (0..num_rows)
+            .map(|idx| {
+                table.get_row(idx).map(|row| {
+                    row.into_iter()
+                        .enumerate()
+                        .filter(|&(column, _)| Some(column) != label_index)
+                        .map(|(_, value)| value)
+                        .collect::<Vec<Numeric>>()
+                })
+            })
+            .collect::<Result<Vec<Vec<Numeric>>, Box<dyn Error>>>()?;
+
+        autoencoder.fit(&rows)?;
+        println!(
+            "Autoencoder reconstruction error: {}",
+            autoencoder.reconstruction_error(&rows)
+        );
+
+        let mut codes = (0..autoencoder.encoding_size)
+            .map(|unit| {
+                let mut column = Column::new();
+                column.set_name(format!("code[{}]", unit));
+                column
+            })
+            .collect::<Vec<Column<Numeric>>>();
+        for row in rows.iter() {
+            for (column, value) in codes.iter_mut().zip(autoencoder.encode(row)) {
+                column.push(value);
+            }
+        }
+
+        // Keep only the label and put the codes in front of it
+        let mut encoded = DataFrame::new();
+        for column in codes {
+            encoded.add_column(column);
+        }
+        if let Some(label_index) = label_index {
+            let label = table
+                .remove_column(label_index)
+                .ok_or("Couldn't find index of column of target value!")?;
+            encoded.add_column(label);
+        }
+        *table = encoded;
+
+        Ok((
+            label_index.map(|_| autoencoder.encoding_size),
+            autoencoder.encoder().to_vec(),
+        ))
+    }
+}
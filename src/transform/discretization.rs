@@ -5,7 +5,7 @@
 /// bins. The second strategy is equal-frequency discretization, where the data is split into a
 /// fixed number of bins, but the number of items per bin is kept fixed.
 
-use super::Transform;
+use super::{FittedTransform, Transform};
 use crate::data::column::Column;
 use crate::types::Numeric;
 
@@ -18,7 +18,7 @@ impl Transform for EqualWidthDiscretization {
     fn apply(
         column: &mut Column<Numeric>,
         parameters: &Option<HashMap<String, Numeric>>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<FittedTransform, Box<dyn Error>> {
         // Check if parameters were given and are correct
         let parameters = parameters.as_ref().ok_or("No parameters given!")?;
         let num_bins = *parameters
@@ -49,7 +49,10 @@ impl Transform for EqualWidthDiscretization {
             *value = bin_range * (((*value - smallest) / bin_range).floor()) + smallest;
         }
 
-        Ok(())
+        Ok(FittedTransform::EqualWidth {
+            smallest,
+            bin_range,
+        })
     }
 }
 
@@ -59,7 +62,7 @@ impl Transform for EqualFrequencyDiscretization {
     fn apply(
         column: &mut Column<Numeric>,
         parameters: &Option<HashMap<String, Numeric>>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<FittedTransform, Box<dyn Error>> {
         // Check that parameters exist and are correct
         let parameters = parameters.as_ref().ok_or("No parameters given!")?;
         let num_bins = *parameters
@@ -88,6 +91,9 @@ impl Transform for EqualFrequencyDiscretization {
         // Sort all items in the copied values
         sorted.sort_by(|(_, x), (_, y)| x.abs().partial_cmp(&y.abs()).unwrap());
 
+        // Largest magnitude and mean of the values of each bin, to discretize new values the same way
+        let mut bins = Vec::with_capacity(num_bins);
+
         // For all bins split values equally by max_items per bin
         for bin_number in 0..(num_bins - 1) {
             let bin_index_start = bin_number * max_items_per_bin;
@@ -102,6 +108,7 @@ impl Transform for EqualFrequencyDiscretization {
                     .ok_or("Could not find index of value in column")?;
                 *value = mean;
             }
+            bins.push((sorted[bin_index_end - 1].1.abs(), mean));
         }
 
         // Modify the last bin to be extended by the residual values of the sorted array
@@ -119,7 +126,8 @@ impl Transform for EqualFrequencyDiscretization {
                 .ok_or("Could not find index of value in column")?;
             *value = last_mean;
         }
+        bins.push((sorted[len_items - 1].1.abs(), last_mean));
 
-        Ok(())
+        Ok(FittedTransform::EqualFrequency { bins })
     }
 }
@@ -2,7 +2,7 @@
 
 /// This file describes a transform to do a log10 of the values in the column
 
-use super::Transform;
+use super::{FittedTransform, Transform};
 use crate::data::column::Column;
 use crate::types::Numeric;
 
@@ -15,7 +15,7 @@ impl Transform for Log10 {
     fn apply(
         column: &mut Column<Numeric>,
         parameters: &Option<HashMap<String, Numeric>>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<FittedTransform, Box<dyn Error>> {
         let offset = if let Some(parameters) = parameters {
             if let Some(input) = parameters.get("offset") {
                 *input
@@ -29,6 +29,6 @@ impl Transform for Log10 {
             (*value) = (*value + offset).log10();
         }
 
-        Ok(())
+        Ok(FittedTransform::Log10 { offset })
     }
 }
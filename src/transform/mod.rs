@@ -1,5 +1,7 @@
-//! This module contains logic that transform items in a Numeric column
+//! This module contains logic that transform items in a Numeric column, or the whole table in the
+//! case of feature extraction
 
+mod autoencoder;
 mod discretization;
 mod zscore;
 mod log10;
@@ -8,19 +10,112 @@ use crate::config::TransformStageConfigs;
 
 use crate::data::data_frame::DataFrame;
 use crate::data::column::Column;
+use crate::models::mlp::Layer;
 use crate::types::Numeric;
 
+use serde::{Deserialize, Serialize};
+
 use std::collections::HashMap;
 use std::error::Error;
 
+// Transforms return what they learned from the column, so it can be repeated on new data
 pub trait Transform {
     fn apply(
         column: &mut Column<Numeric>,
         parameters: &Option<HashMap<String, Numeric>>,
-    ) -> Result<(), Box<dyn Error>>;
+    ) -> Result<FittedTransform, Box<dyn Error>>;
+}
+
+type TransformFnPtr = fn(&mut Column<Numeric>, &Option<HashMap<String, Numeric>>) -> Result<FittedTransform, Box<dyn Error>>;
+
+/// What a column transform learned from the training table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum FittedTransform {
+    ZScore { mean: Numeric, deviation: Numeric },
+    Log10 { offset: Numeric },
+    EqualWidth { smallest: Numeric, bin_range: Numeric },
+    // Largest magnitude and mean of the values of each bin, by increasing magnitude
+    EqualFrequency { bins: Vec<(Numeric, Numeric)> },
+}
+
+impl FittedTransform {
+    /// Transforms a new value the same way the training values were transformed
+    pub fn transform(&self, value: Numeric) -> Numeric {
+        match self {
+            Self::ZScore { mean, deviation } => (value - mean) / deviation,
+            Self::Log10 { offset } => (value + offset).log10(),
+            Self::EqualWidth {
+                smallest,
+                bin_range,
+            } => bin_range * ((value - smallest) / bin_range).floor() + smallest,
+            Self::EqualFrequency { bins } => bins
+                .iter()
+                .find(|(largest, _)| value.abs() <= *largest)
+                .or(bins.last())
+                .map_or(value, |(_, mean)| *mean),
+        }
+    }
+
+    /// Maps a transformed value back to the original units. Discretized values are bin values
+    /// already in the original units, so they are returned as they are.
+    pub fn inverse(&self, value: Numeric) -> Numeric {
+        match self {
+            Self::ZScore { mean, deviation } => value * deviation + mean,
+            Self::Log10 { offset } => Numeric::powf(10.0, value) - offset,
+            Self::EqualWidth { .. } | Self::EqualFrequency { .. } => value,
+        }
+    }
 }
 
-type TransformFnPtr = fn(&mut Column<Numeric>, &Option<HashMap<String, Numeric>>) -> Result<(), Box<dyn Error>>;
+/// A transform stage as it is saved with a model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum FittedStage {
+    Column {
+        index: usize,
+        transform: FittedTransform,
+    },
+    // The feature columns are replaced by their encoding through the encoder layers
+    Autoencoder { encoder: Vec<Layer> },
+}
+
+impl FittedStage {
+    /// Repeats the stage on a row of the table. Returns the transformed row and the index of the
+    /// label column in it.
+    pub fn apply_row(
+        &self,
+        row: Vec<Numeric>,
+        label_index: Option<usize>,
+    ) -> Result<(Vec<Numeric>, Option<usize>), Box<dyn Error>> {
+        match self {
+            Self::Column { index, transform } => {
+                let mut row = row;
+                let value = row
+                    .get_mut(*index)
+                    .ok_or(format!("The row has no column {} to transform!", index))?;
+                *value = transform.transform(*value);
+                Ok((row, label_index))
+            }
+            Self::Autoencoder { encoder } => {
+                let features = row
+                    .iter()
+                    .enumerate()
+                    .filter(|&(column, _)| Some(column) != label_index)
+                    .map(|(_, value)| *value)
+                    .collect::<Vec<Numeric>>();
+                let mut encoded = encoder
+                    .iter()
+                    .fold(features, |values, layer| layer.forward(&values));
+                let encoding_size = encoded.len();
+                if let Some(label_index) = label_index {
+                    encoded.push(row[label_index]);
+                }
+                Ok((encoded, label_index.map(|_| encoding_size)))
+            }
+        }
+    }
+}
 
 pub fn get_transform(name: &str) -> Result<TransformFnPtr, Box<dyn Error>> {
     match name {
@@ -32,16 +127,39 @@ pub fn get_transform(name: &str) -> Result<TransformFnPtr, Box<dyn Error>> {
     }
 }
 
+/// Runs every transform in order. Column transforms need the index of their column, feature
+/// extraction replaces the feature columns. Returns the index of the label column, if any, in the
+/// resulting table, and what every stage learned from the table.
 pub fn apply(
     table: &mut DataFrame<Numeric>,
+    label_index: Option<usize>,
     parameters: &[TransformStageConfigs],
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(Option<usize>, Vec<FittedStage>), Box<dyn Error>> {
+    let mut label_index = label_index;
+    let mut stages = Vec::new();
     for parameter in parameters.iter() {
+        if parameter.name == "autoencoder" {
+            let (new_label_index, encoder) =
+                autoencoder::AutoencoderEncoding::apply(
+                table,
+                label_index,
+                &parameter.parameters,
+                &parameter.hyperparameters,
+            )?;
+            label_index = new_label_index;
+            stages.push(FittedStage::Autoencoder { encoder });
+            continue;
+        }
+
         let transform = get_transform(parameter.name.as_str())?;
-        if let Some(column) = table.get_column_idx_mut(parameter.index) {
-            transform(column, &parameter.parameters)?;
+        let index = parameter
+            .index
+            .ok_or(format!("The {} transform needs a column index!", parameter.name))?;
+        if let Some(column) = table.get_column_idx_mut(index) {
+            let transform = transform(column, &parameter.parameters)?;
+            stages.push(FittedStage::Column { index, transform });
         }
     }
 
-    Ok(())
+    Ok((label_index, stages))
 }
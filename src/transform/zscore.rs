@@ -5,7 +5,7 @@
 
 use std::collections::HashMap;
 
-use super::Transform;
+use super::{FittedTransform, Transform};
 use crate::data::column::Column;
 use crate::types::Numeric;
 
@@ -17,7 +17,7 @@ impl Transform for ZScoreNormalization {
     fn apply(
         column: &mut Column<Numeric>,
         _parameters: &Option<HashMap<String, Numeric>>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<FittedTransform, Box<dyn Error>> {
         // Sum all values in the column
         let sum = column.values().fold(0.0, |acc, &v| acc + v);
         let count = column.values().count();
@@ -41,6 +41,9 @@ impl Transform for ZScoreNormalization {
             (*value) = ((*value) - mean) / std_deviation;
         }
 
-        Ok(())
+        Ok(FittedTransform::ZScore {
+            mean,
+            deviation: std_deviation,
+        })
     }
 }
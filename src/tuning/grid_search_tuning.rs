@@ -5,7 +5,7 @@ use std::collections::HashMap;
 use std::error::Error;
 
 pub fn get_hyperparameter_combinations(
-    tuning_parameters: &HashMap<String, Vec<f64>>,
+    tuning_parameters: &HashMap<String, Vec<String>>,
 ) -> Result<Vec<HashMap<String, String>>, Box<dyn Error>> {
     // Create combinations of hyperparameters
     let combinations = tuning_parameters.iter().map(|(_, range)| range.iter()).multi_cartesian_product();
@@ -15,7 +15,7 @@ pub fn get_hyperparameter_combinations(
     for combination in combinations {
         let mut hyperparameter_values = HashMap::new();
         for ((param, _), value) in tuning_parameters.iter().zip(combination) {
-            hyperparameter_values.insert(param.clone(), value.to_string());
+            hyperparameter_values.insert(param.clone(), value.clone());
         }
         hyperparameter_combinations.push(hyperparameter_values)
     }
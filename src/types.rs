@@ -5,3 +5,6 @@
 pub const NUMERIC_DIGIT_PRECISION: f64 = 1e-8;
 
 pub type Numeric = f64;
+
+/// Probability of each class code, sorted by class code
+pub type ClassProbabilities = Vec<(Numeric, f64)>;
@@ -33,6 +33,16 @@ impl Partitioner for KFold {
 
         let label_column = table.get_column_idx(label_column_idx).unwrap();
         let num_samples = label_column.values().len();
+        if k < 2 {
+            return Err("Number of folds is less than 2!".into());
+        }
+        if num_samples < k {
+            return Err(format!(
+                "There are {} rows, fewer than the {} folds of the k-fold!",
+                num_samples, k
+            )
+            .into());
+        }
         let fold_size = num_samples / k;
 
         // Shuffle indexes
@@ -44,6 +44,23 @@ impl Partitioner for StratifiedKFold {
             index_list.push(idx);
         });
 
+        // Every class needs a row in every fold, smaller classes would give empty folds
+        if k < 2 {
+            return Err("Number of folds is less than 2!".into());
+        }
+        if let Some((label, index_list)) = label_indices
+            .iter()
+            .find(|(_, index_list)| index_list.len() < k)
+        {
+            return Err(format!(
+                "Class {} has {} rows, fewer than the {} folds of the stratified k-fold!",
+                (*label as f64) * NUMERIC_DIGIT_PRECISION,
+                index_list.len(),
+                k
+            )
+            .into());
+        }
+
         // Shuffle the list of indexes for each class label
         label_indices
             .values_mut()